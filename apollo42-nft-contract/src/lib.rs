@@ -1,17 +1,19 @@
+use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApproval;
 use near_contract_standards::non_fungible_token::core::{
-    NonFungibleTokenCore, NonFungibleTokenResolver,
+    NonFungibleTokenResolver,
 };
 use near_contract_standards::non_fungible_token::metadata::{
     NFTContractMetadata, NonFungibleTokenMetadataProvider, TokenMetadata, NFT_METADATA_SPEC,
 };
 use near_contract_standards::non_fungible_token::NonFungibleToken;
 use near_contract_standards::non_fungible_token::{Token, TokenId};
+use near_contract_standards::storage_management::StorageBalance;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, UnorderedMap, UnorderedSet};
+use near_sdk::collections::{LazyOption, LookupMap, LookupSet, UnorderedMap, UnorderedSet, Vector};
 use near_sdk::json_types::{ValidAccountId, U128, U64};
 use near_sdk::{
     assert_one_yocto, env, near_bindgen, serde_json::json, AccountId, Balance, BorshStorageKey,
-    PanicOnDefault, Promise, PromiseOrValue, Gas, ext_contract, Timestamp
+    PanicOnDefault, Promise, PromiseOrValue, PromiseResult, Gas, ext_contract, Timestamp
 };
 use near_sdk::serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -19,6 +21,7 @@ use near_sdk::env::is_valid_account_id;
 
 pub mod event;
 pub use event::NearEvent;
+use event::NftMintData;
 
 /// between token_series_id and edition number e.g. 42:2 where 42 is series and 2 is edition
 pub const TOKEN_DELIMETER: char = ':';
@@ -31,19 +34,68 @@ const GAS_FOR_RESOLVE_TRANSFER: Gas = 10_000_000_000_000;
 const GAS_FOR_NFT_TRANSFER_CALL: Gas = 30_000_000_000_000 + GAS_FOR_RESOLVE_TRANSFER;
 const GAS_FOR_NFT_APPROVE: Gas = 10_000_000_000_000;
 const GAS_FOR_MINT: Gas = 90_000_000_000_000;
+const GAS_FOR_TREASURY_CALLBACK: Gas = 5_000_000_000_000;
 const NO_DEPOSIT: Balance = 0;
 const MAX_PRICE: Balance = 1_000_000_000 * 10u128.pow(24);
+/// series royalty total in bps, 50%
+const MAX_ROYALTY_BPS: u32 = 5000;
+const DEFAULT_NON_MINTABLE_GRACE_PERIOD: TimestampSec = 86_400;
+// delay between request_retirement and retire
+const RETIREMENT_TIMELOCK: TimestampSec = 7 * 86_400;
+const DEFAULT_MAX_APPROVALS_PER_TOKEN: u32 = 4;
+// nft_revert_mint is only allowed this long after the mint, in nanoseconds
+const REVERT_MINT_WINDOW: Timestamp = 60_000_000_000;
+// ownership_changes keeps only the latest records, older ones are overwritten
+const OWNERSHIP_CHANGES_CAP: u64 = 1_000;
+const MAX_SERIES_ICON_LEN: usize = 16_384;
+// storage estimate for nft_buy_change until the first nft_buy is recorded
+const DEFAULT_BUY_STORAGE_USAGE: u64 = 1_128;
+// gas-safe input sizes of batch methods, exposed by batch_limits
+const MAX_BATCH_MINT: usize = 50;
+const MAX_BATCH_BURN: usize = 50;
+const MAX_BATCH_TRANSFER: usize = 50;
+const MAX_BATCH_APPROVE: usize = 20;
+const MAX_BATCH_PAYOUT: usize = 50;
+const MAX_BATCH_SET_NON_MINTABLE: usize = 50;
+// nft_series_by_popularity only ranks this many series, in creation order
+const MAX_POPULARITY_SCAN: usize = 1_000;
+// nft_tokens_for_owner_sorted sorts in memory, owners holding more must use nft_tokens_for_owner
+const MAX_SORTED_TOKENS_FOR_OWNER: u64 = 1_000;
+// nft_get_series_sorted sorts every series id in memory
+const MAX_SORTED_SERIES: u64 = 2_000;
+// tokens included in nft_export_series, the rest is read with nft_series_holders
+const MAX_EXPORT_TOKENS: u64 = 100;
+// primary sales vest from the next multiple of this, sales sharing a start share one grant
+const VESTING_BUCKET_SECS: TimestampSec = 86_400;
 
 pub type TokenSeriesId = String;
 pub type TimestampSec = u32;
 pub type ContractAndTokenId = String;
 
+/// Listing payload forwarded as `msg` to the marketplace's nft_on_transfer
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ListingArgs {
+    pub price: U128,
+    pub ft_token_id: AccountId,
+    pub expiry: Option<U64>,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Payout {
     pub payout: HashMap<AccountId, U128>,
 }
 
+/// nft_payout outcome without panicking, exactly one of payout and error is set
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PayoutResult {
+    pub ok: bool,
+    pub payout: Option<Payout>,
+    pub error: Option<String>,
+}
+
 #[ext_contract(ext_non_fungible_token_receiver)]
 trait NonFungibleTokenReceiver {
     /// Returns `true` if the token should be returned back to the sender.
@@ -76,6 +128,8 @@ trait NonFungibleTokenResolver {
         token_id: TokenId,
         approved_account_ids: Option<HashMap<AccountId, u64>>,
     ) -> bool;
+
+    fn on_treasury_transfer_failed(&mut self, amount: U128);
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
@@ -85,7 +139,131 @@ pub struct TokenSeries {
 	tokens: UnorderedSet<TokenId>,
     price: Option<Balance>,
     is_mintable: bool,
-    royalty: HashMap<AccountId, u32>
+    royalty: HashMap<AccountId, u32>,
+    auto_approve: bool,
+    // royalty multiplier in bps per resale count, last step applies afterwards
+    royalty_decay: Vec<u32>,
+    primary_split: Option<Vec<(AccountId, u32)>>,
+    // creator mints draw storage from the receiver's storage balance when available
+    charge_receiver_storage: bool,
+    royalty_rounding: RoundingMode,
+    burnable_by_owner: bool,
+    // editions below this are all minted, explicit editions may sit above it
+    next_edition: u64,
+    // false while placeholder metadata is shown, revealed_metadata replaces it on reveal
+    revealed: bool,
+    revealed_metadata: Option<TokenMetadata>,
+    // requested non-mintable, minting continues until this time unless cancelled
+    non_mintable_at: Option<TimestampSec>,
+    // buyers may pass their own token extra (e.g. an engraved name) to nft_buy
+    allow_custom_mint_metadata: bool,
+    series_contract_metadata: Option<SeriesMeta>,
+    // a token rejected by nft_on_transfer is burned instead of returned
+    burn_on_transfer_reject: bool,
+    // nft_buy stops once the series holds this many tokens, creator mints go up to copies
+    max_sellable: Option<u64>,
+    // shown by wallets instead of the contract icon, usually a data URI
+    icon: Option<String>,
+    // token the price is denominated in, None for NEAR
+    price_currency: Option<CurrencyInfo>,
+    // MIME type of metadata.media, e.g. "image/png"
+    media_type: Option<String>,
+    // sets expires_at of minted tokens, expired tokens cannot be transferred
+    token_lifetime_secs: Option<u32>,
+}
+
+/// TokenSeries as stored by the released contract, rewritten by migrate_series
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TokenSeriesV1 {
+    metadata: TokenMetadata,
+    creator_id: AccountId,
+    tokens: UnorderedSet<TokenId>,
+    price: Option<Balance>,
+    is_mintable: bool,
+    royalty: HashMap<AccountId, u32>,
+}
+
+impl From<TokenSeriesV1> for TokenSeries {
+    /// Settings added since the release start out with the released behaviour
+    fn from(prev: TokenSeriesV1) -> Self {
+        // released mints numbered editions tokens.len() + 1 and burns kept the id in tokens
+        let next_edition = prev.tokens.len() + 1;
+        TokenSeries {
+            metadata: prev.metadata,
+            creator_id: prev.creator_id,
+            tokens: prev.tokens,
+            price: prev.price,
+            is_mintable: prev.is_mintable,
+            royalty: prev.royalty,
+            auto_approve: false,
+            royalty_decay: Vec::new(),
+            primary_split: None,
+            charge_receiver_storage: false,
+            royalty_rounding: RoundingMode::Floor,
+            burnable_by_owner: true,
+            next_edition,
+            revealed: true,
+            revealed_metadata: None,
+            non_mintable_at: None,
+            allow_custom_mint_metadata: false,
+            series_contract_metadata: None,
+            burn_on_transfer_reject: false,
+            max_sellable: None,
+            icon: None,
+            price_currency: None,
+            media_type: None,
+            token_lifetime_secs: None,
+        }
+    }
+}
+
+impl TokenSeries {
+    /// is_mintable with a pending non-mintable request applied once its grace period is over
+    fn mintable(&self) -> bool {
+        self.is_mintable && self.non_mintable_at
+            .map(|non_mintable_at| to_sec(env::block_timestamp()) < non_mintable_at)
+            .unwrap_or(true)
+    }
+
+    /// Why nft_buy can't mint from the series, None when it is purchasable
+    fn not_for_sale_reason(&self) -> Option<&'static str> {
+        let is_sold_out = self.metadata.copies
+            .map(|copies| self.tokens.len() >= copies)
+            .unwrap_or(false);
+
+        let is_sale_limit_reached = self.max_sellable
+            .map(|max_sellable| self.tokens.len() >= max_sellable)
+            .unwrap_or(false);
+
+        if is_sold_out {
+            Some("sold out")
+        } else if is_sale_limit_reached {
+            Some("sale limit reached")
+        } else if !self.mintable() {
+            Some("paused")
+        } else if self.price.is_none() {
+            Some("not for sale")
+        } else {
+            None
+        }
+    }
+
+    fn first_free_edition(&self, token_series_id: &TokenSeriesId) -> u64 {
+        let mut edition = self.next_edition;
+        while self.tokens.contains(&format!("{}{}{}", token_series_id, TOKEN_DELIMETER, edition)) {
+            edition += 1;
+        }
+        edition
+    }
+}
+
+/// How royalty payouts are rounded, the owner residual absorbs the difference
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RoundingMode {
+    Floor,
+    Round,
+    Ceil,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -95,7 +273,10 @@ pub struct TokenSeriesJson {
 	metadata: TokenMetadata,
 	creator_id: AccountId,
     royalty: HashMap<AccountId, u32>,
-    transaction_fee: Option<U128>
+    transaction_fee: Option<U128>,
+    burnable_by_owner: bool,
+    // effective at the current block time, a passed non_mintable_at already reads false
+    is_mintable: bool,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
@@ -106,6 +287,200 @@ pub struct TransactionFee {
     pub current_fee: u16,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SaleStatus {
+    pub price: Option<U128>,
+    pub available: bool,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractStatus {
+    pub owner_id: AccountId,
+    pub treasury_id: AccountId,
+    pub secondary_treasury_id: AccountId,
+    pub current_fee: u16,
+    pub scheduled_fee: Option<u16>,
+    pub scheduled_fee_start_time: Option<TimestampSec>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeSchedule {
+    pub current: u16,
+    pub next: Option<u16>,
+    pub starts_at: Option<TimestampSec>,
+    pub effective_now: u16,
+}
+
+/// Fields replaced when cloning a series, None keeps the source value
+#[derive(Serialize, Deserialize, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CloneOverrides {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub price: Option<U128>,
+    pub copies: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchLimits {
+    pub mint: u32,
+    pub burn: u32,
+    pub transfer: u32,
+    pub approve: u32,
+    pub payout: u32,
+    pub set_non_mintable: u32,
+}
+
+/// Owner change of a token, from is None on mint and to is None on burn
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChangeRecord {
+    pub token_id: TokenId,
+    pub from: Option<AccountId>,
+    pub to: Option<AccountId>,
+    pub block_height: U64,
+}
+
+/// Series config and holders for backups and migrations to another contract.
+/// Holders past MAX_EXPORT_TOKENS are read with nft_series_holders from index next_index.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SeriesExport {
+    pub token_series_id: TokenSeriesId,
+    pub creator_id: AccountId,
+    pub metadata: TokenMetadata,
+    pub royalty: HashMap<AccountId, u32>,
+    pub price: Option<U128>,
+    pub is_mintable: bool,
+    pub auto_approve: bool,
+    pub royalty_decay: Vec<u32>,
+    pub primary_split: Option<Vec<(AccountId, u32)>>,
+    pub charge_receiver_storage: bool,
+    pub royalty_rounding: RoundingMode,
+    pub burnable_by_owner: bool,
+    pub revealed: bool,
+    pub revealed_metadata: Option<TokenMetadata>,
+    pub allow_custom_mint_metadata: bool,
+    pub series_contract_metadata: Option<SeriesMeta>,
+    pub burn_on_transfer_reject: bool,
+    pub max_sellable: Option<u64>,
+    pub icon: Option<String>,
+    pub price_currency: Option<CurrencyInfo>,
+    pub media_type: Option<String>,
+    pub token_lifetime_secs: Option<u32>,
+    pub supply: U64,
+    pub tokens: Vec<(TokenId, AccountId)>,
+    // from_index of the next nft_series_holders page, None when tokens holds every holder
+    pub next_index: Option<U128>,
+}
+
+/// Primary sale proceeds vest linearly over duration seconds, nothing is released before cliff seconds
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Vesting {
+    pub cliff: u32,
+    pub duration: u32,
+}
+
+/// Proceeds of the primary sales of one day held under the vesting schedule active at the sales
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct VestingGrant {
+    pub amount: Balance,
+    pub released: Balance,
+    pub start: TimestampSec,
+    pub vesting: Vesting,
+}
+
+impl VestingGrant {
+    fn vested(&self, now: TimestampSec) -> Balance {
+        let elapsed = now.saturating_sub(self.start);
+        if elapsed < self.vesting.cliff {
+            0
+        } else if elapsed >= self.vesting.duration {
+            self.amount
+        } else {
+            self.amount * Balance::from(elapsed) / Balance::from(self.vesting.duration)
+        }
+    }
+}
+
+/// Running totals per creator, maintained on series creation, mint and nft_buy
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct CreatorTotals {
+    pub series_count: u64,
+    pub total_minted: u64,
+    pub total_volume: Balance,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SeriesSales {
+    pub total_sold: U64,
+    pub minted_count: U64,
+    pub supply: U64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CreatorStats {
+    pub series_count: U64,
+    pub total_minted: U64,
+    pub total_volume: U128,
+    pub pending_payout: U128,
+}
+
+/// FT a series price is denominated in, mirrors the ft_metadata fields UIs need
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CurrencyInfo {
+    pub contract: AccountId,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+/// Collection-level metadata for a series presented as its own sub-collection
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SeriesMeta {
+    pub name: String,
+    pub symbol: String,
+    pub banner: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Maximum byte length of series metadata strings, None is unlimited
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MetadataLimits {
+    pub title: Option<u32>,
+    pub description: Option<u32>,
+    pub media: Option<u32>,
+    pub extra: Option<u32>,
+    pub reference: Option<u32>,
+}
+
+impl MetadataLimits {
+    pub fn assert_within(&self, token_metadata: &TokenMetadata) {
+        let fields = [
+            ("title", &token_metadata.title, self.title),
+            ("description", &token_metadata.description, self.description),
+            ("media", &token_metadata.media, self.media),
+            ("extra", &token_metadata.extra, self.extra),
+            ("reference", &token_metadata.reference, self.reference),
+        ];
+        for (name, value, max_size) in fields.iter() {
+            if let (Some(value), Some(max_size)) = (value, max_size) {
+                assert!(value.len() <= *max_size as usize, "Field {} exceeds max size", name);
+            }
+        }
+    }
+}
+
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct MarketDataTransactionFee {
     pub transaction_fee: UnorderedMap<TokenSeriesId, u128>
@@ -118,11 +493,23 @@ pub struct ContractV1 {
     tokens: NonFungibleToken,
     metadata: LazyOption<NFTContractMetadata>,
     // CUSTOM
-	token_series_by_id: UnorderedMap<TokenSeriesId, TokenSeries>,
+	token_series_by_id: UnorderedMap<TokenSeriesId, TokenSeriesV1>,
     treasury_id: AccountId,
     transaction_fee: TransactionFee
 }
 
+/// Layout of the released contract, ContractV1 plus the market data fees
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct ContractV2 {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    // CUSTOM
+    token_series_by_id: UnorderedMap<TokenSeriesId, TokenSeriesV1>,
+    treasury_id: AccountId,
+    transaction_fee: TransactionFee,
+    market_data_transaction_fee: MarketDataTransactionFee
+}
+
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
@@ -130,9 +517,79 @@ pub struct Contract {
     metadata: LazyOption<NFTContractMetadata>,
     // CUSTOM
     token_series_by_id: UnorderedMap<TokenSeriesId, TokenSeries>,
-    treasury_id: AccountId,
+    // receives the nft_buy fee
+    primary_treasury_id: AccountId,
+    // receives secondary_treasury_bps of resale payouts
+    secondary_treasury_id: AccountId,
     transaction_fee: TransactionFee,
-    market_data_transaction_fee: MarketDataTransactionFee
+    market_data_transaction_fee: MarketDataTransactionFee,
+    enforce_metadata_hashes: bool,
+    auto_approve_market: Option<AccountId>,
+    upgrade_inputs_by_series: LookupMap<TokenSeriesId, Vec<TokenSeriesId>>,
+    resale_count_by_token: LookupMap<TokenId, u32>,
+    series_id_by_idempotency_key: UnorderedMap<String, TokenSeriesId>,
+    metadata_limits: MetadataLimits,
+    total_minted: u128,
+    total_burned: u128,
+    storage_deposits: LookupMap<AccountId, Balance>,
+    trusted_contracts: UnorderedSet<AccountId>,
+    locker_by_token: LookupMap<TokenId, AccountId>,
+    max_copies_per_series: Option<u64>,
+    token_royalty_override: LookupMap<TokenId, HashMap<AccountId, u32>>,
+    volume_by_series: LookupMap<TokenSeriesId, Balance>,
+    // paid nft_buy sales and burns per series, token_series.tokens keeps burned editions
+    sold_by_series: LookupMap<TokenSeriesId, u64>,
+    burned_by_series: LookupMap<TokenSeriesId, u64>,
+    pending_payouts: LookupMap<AccountId, Balance>,
+    creator_totals: LookupMap<AccountId, CreatorTotals>,
+    // nft_create_series requires one of these media types unless empty
+    allowed_media_types: Vec<String>,
+    // next receiver index of nft_batch_mint_range, keyed by batch_mint_cursor_key
+    batch_mint_cursors: LookupMap<Vec<u8>, u64>,
+    // nft_buy receivers wait this long between mints, creator mints are not throttled
+    mint_cooldown_secs: Option<u32>,
+    last_mint_time: LookupMap<AccountId, TimestampSec>,
+    // primary sale proceeds are held and released by withdraw_vested while set
+    vesting: Option<Vesting>,
+    vesting_balances: LookupMap<AccountId, Vec<VestingGrant>>,
+    min_treasury_cut: Balance,
+    count_by_owner_series: LookupMap<(AccountId, TokenSeriesId), u32>,
+    // tokens up to the cursor are counted in count_by_owner_series, see backfill_owner_series_counts
+    owner_series_backfill_cursor: Option<TokenId>,
+    owner_series_backfill_done: bool,
+    // series ids are never reused, even if the series map shrinks
+    next_series_id: u64,
+    retirement_available_at: Option<TimestampSec>,
+    // irreversible, no more mints or series once set
+    retired: bool,
+    // series below this index of token_series_by_id are still stored as TokenSeriesV1
+    series_to_migrate: u64,
+    series_migrated: u64,
+    prepend_base_uri: bool,
+    // lifetime mints keyed by "{token_series_id}:{account_id}", unaffected by transfer/burn
+    minted_per_owner: LookupMap<String, u64>,
+    non_mintable_grace_period: TimestampSec,
+    max_approvals_per_token: u32,
+    require_creator_royalty: bool,
+    withdrawals_paused: bool,
+    // royalty recipients must be named accounts, implicit accounts may be unclaimable
+    strict_royalty_accounts: bool,
+    // every account that ever held a token, counted against max_distinct_owners
+    holders: LookupSet<AccountId>,
+    distinct_owners: u64,
+    max_distinct_owners: Option<u64>,
+    secondary_treasury_bps: u32,
+    // tokens transferred within REVERT_MINT_WINDOW of their mint, these can't be reverted
+    transferred_in_revert_window: LookupSet<TokenId>,
+    // tokens minted by their creator, only these can be reverted, true if the mint added a holder
+    creator_minted: LookupMap<TokenId, bool>,
+    // ring buffer of the latest OWNERSHIP_CHANGES_CAP changes
+    ownership_changes: Vector<ChangeRecord>,
+    ownership_changes_total: u64,
+    // bytes used by the latest nft_buy, the storage estimate of nft_buy_change
+    last_buy_storage_usage: u64,
+    // nft_buy only mints to listed receivers while a series has an allowlist
+    allowlist_by_series: LookupMap<TokenSeriesId, UnorderedSet<AccountId>>,
 }
 
 const DATA_IMAGE_SVG_APOLLO42_ICON: &str = "data:image/svg+xml,%3Csvg%20xmlns%3D%22http%3A%2F%2Fwww.w3.org%2F2000%2Fsvg%22%20width%3D%221024%22%20height%3D%221024%22%20viewBox%3D%220%200%2066%2050%22%20fill%3D%22none%22%3E%20%3Cg%20clip-path%3D%22url%28%23clip0_14_98%29%22%3E%20%3Cpath%20d%3D%22M54.6973%2011.1756L65.2359%200L51.2844%2010.2654L43.3686%2016.089L39.3041%2019.0784C37.939%2020.0839%2024.2129%2030.1796%2019.4038%2033.7172L18.2786%2034.5448C18.1111%2034.6689%2017.9787%2034.7661%2017.8898%2034.8303L17.8608%2034.8509C17.7884%2034.9047%2017.7491%2034.9337%2017.7491%2034.9337L16.9445%2035.5254L11.488%2039.5388L0%2047.9899L11.6804%2043.8378C13.2586%2043.2772%2015.0147%2043.5606%2016.3343%2044.5929C20.5353%2047.8864%2025.8284%2049.8456%2031.5806%2049.8352C45.1888%2049.8125%2056.4017%2038.4155%2056.23%2024.7904C56.1845%2021.2817%2055.4109%2017.9468%2054.0499%2014.9346C53.479%2013.6727%2053.7458%2012.1831%2054.6973%2011.1756ZM31.7068%2040.0022C28.6745%2040.0354%2025.849%2039.1602%2023.4828%2037.6314C23.4704%2037.6169%2022.7133%2036.7232%2022.6864%2035.9515C22.6575%2035.1716%2023.1642%2034.5675%2023.1642%2034.5675L42.9528%2019.9949C44.1484%2019.4012%2045.5921%2020.0528%2045.9293%2021.3479C46.2561%2022.6016%2046.4236%2023.9174%2046.4091%2025.2766C46.3243%2033.3324%2039.755%2039.9133%2031.7068%2040.0022Z%22%20fill%3D%22%235C5C5C%22%20fill-opacity%3D%220.2%22%2F%3E%20%3Cpath%20d%3D%22M54.6973%2011.1756L65.2359%200L51.2844%2010.2654L43.3686%2016.089L39.3041%2019.0784C37.939%2020.0839%2024.2129%2030.1796%2019.4038%2033.7172L18.2786%2034.5448C18.1111%2034.6689%2017.9787%2034.7661%2017.8898%2034.8303L17.8608%2034.8509C17.7884%2034.9047%2017.7491%2034.9337%2017.7491%2034.9337L16.9445%2035.5254L11.488%2039.5388L0%2047.9899L11.6804%2043.8378C13.2586%2043.2772%2015.0147%2043.5606%2016.3343%2044.5929C20.5353%2047.8864%2025.8284%2049.8456%2031.5806%2049.8352C45.1888%2049.8125%2056.4017%2038.4155%2056.23%2024.7904C56.1845%2021.2817%2055.4109%2017.9468%2054.0499%2014.9346C53.479%2013.6727%2053.7458%2012.1831%2054.6973%2011.1756ZM31.7068%2040.0022C28.6745%2040.0354%2025.849%2039.1602%2023.4828%2037.6314C23.4704%2037.6169%2022.7133%2036.7232%2022.6864%2035.9515C22.6575%2035.1716%2023.1642%2034.5675%2023.1642%2034.5675L42.9528%2019.9949C44.1484%2019.4012%2045.5921%2020.0528%2045.9293%2021.3479C46.2561%2022.6016%2046.4236%2023.9174%2046.4091%2025.2766C46.3243%2033.3324%2039.755%2039.9133%2031.7068%2040.0022Z%22%20fill%3D%22url%28%23paint0_linear_14_98%29%22%2F%3E%20%3Cpath%20d%3D%22M54.6973%2011.1756L65.2359%200L51.2844%2010.2654L43.3686%2016.089L39.3041%2019.0784C37.939%2020.0839%2024.2129%2030.1796%2019.4038%2033.7172L18.2786%2034.5448C18.1111%2034.6689%2017.9787%2034.7661%2017.8898%2034.8303L17.8608%2034.8509C17.7884%2034.9047%2017.7491%2034.9337%2017.7491%2034.9337L16.9445%2035.5254L11.488%2039.5388L0%2047.9899L11.6804%2043.8378C13.2586%2043.2772%2015.0147%2043.5606%2016.3343%2044.5929C20.5353%2047.8864%2025.8284%2049.8456%2031.5806%2049.8352C45.1888%2049.8125%2056.4017%2038.4155%2056.23%2024.7904C56.1845%2021.2817%2055.4109%2017.9468%2054.0499%2014.9346C53.479%2013.6727%2053.7458%2012.1831%2054.6973%2011.1756ZM31.7068%2040.0022C28.6745%2040.0354%2025.849%2039.1602%2023.4828%2037.6314C23.4704%2037.6169%2022.7133%2036.7232%2022.6864%2035.9515C22.6575%2035.1716%2023.1642%2034.5675%2023.1642%2034.5675L42.9528%2019.9949C44.1484%2019.4012%2045.5921%2020.0528%2045.9293%2021.3479C46.2561%2022.6016%2046.4236%2023.9174%2046.4091%2025.2766C46.3243%2033.3324%2039.755%2039.9133%2031.7068%2040.0022Z%22%20fill%3D%22url%28%23paint1_linear_14_98%29%22%2F%3E%20%3Cpath%20d%3D%22M50.4405%209.0054C47.0855%205.0871%2042.9383%203.03486%2041.0085%202.23216C40.4252%201.99632%2039.8295%201.7791%2039.2214%201.58463C26.393%20-2.51573%2012.5284%204.49956%208.26333%2017.262C5.83707%2024.5214%206.9685%2032.087%2010.6937%2038.0989L14.1666%2035.5563L18.6633%2032.2649L18.7936%2032.1697C17.958%2030.8519%2017.4347%2029.5382%2017.1534%2028.0569C17.1058%2027.9349%2017.0686%2027.8087%2017.0438%2027.6742C16.6321%2025.3386%2016.7728%2022.8705%2017.5671%2020.4541C17.6912%2020.0755%2017.836%2019.7052%2017.9911%2019.3411C20.2043%2013.9291%2025.5181%2010.1163%2031.7254%2010.1163C36.0567%2010.1163%2039.8626%2011.8314%2042.5556%2014.7608C42.5577%2014.7629%2042.5556%2014.7711%2042.5536%2014.7773L45.7658%2012.4272L50.4405%209.0054Z%22%20fill%3D%22url%28%23paint2_linear_14_98%29%22%2F%3E%20%3Cpath%20d%3D%22M50.4405%209.0054C47.0855%205.0871%2042.9383%203.03486%2041.0085%202.23216C40.4252%201.99632%2039.8295%201.7791%2039.2214%201.58463C26.393%20-2.51573%2012.5284%204.49956%208.26333%2017.262C5.83707%2024.5214%206.9685%2032.087%2010.6937%2038.0989L14.1666%2035.5563L18.6633%2032.2649L18.7936%2032.1697C17.958%2030.8519%2017.4347%2029.5382%2017.1534%2028.0569C17.1058%2027.9349%2017.0686%2027.8087%2017.0438%2027.6742C16.6321%2025.3386%2016.7728%2022.8705%2017.5671%2020.4541C17.6912%2020.0755%2017.836%2019.7052%2017.9911%2019.3411C20.2043%2013.9291%2025.5181%2010.1163%2031.7254%2010.1163C36.0567%2010.1163%2039.8626%2011.8314%2042.5556%2014.7608C42.5577%2014.7629%2042.5556%2014.7711%2042.5536%2014.7773L45.7658%2012.4272L50.4405%209.0054Z%22%20fill%3D%22url%28%23paint3_linear_14_98%29%22%2F%3E%20%3Cpath%20d%3D%22M50.4405%209.0054C47.0855%205.0871%2042.9383%203.03486%2041.0085%202.23216C40.4252%201.99632%2039.8295%201.7791%2039.2214%201.58463C26.393%20-2.51573%2012.5284%204.49956%208.26333%2017.262C5.83707%2024.5214%206.9685%2032.087%2010.6937%2038.0989L14.1666%2035.5563L18.6633%2032.2649L18.7936%2032.1697C17.958%2030.8519%2017.4347%2029.5382%2017.1534%2028.0569C17.1058%2027.9349%2017.0686%2027.8087%2017.0438%2027.6742C16.6321%2025.3386%2016.7728%2022.8705%2017.5671%2020.4541C17.6912%2020.0755%2017.836%2019.7052%2017.9911%2019.3411C20.2043%2013.9291%2025.5181%2010.1163%2031.7254%2010.1163C36.0567%2010.1163%2039.8626%2011.8314%2042.5556%2014.7608C42.5577%2014.7629%2042.5556%2014.7711%2042.5536%2014.7773L45.7658%2012.4272L50.4405%209.0054Z%22%20fill%3D%22url%28%23paint4_linear_14_98%29%22%2F%3E%20%3C%2Fg%3E%20%3Cdefs%3E%20%3ClinearGradient%20id%3D%22paint0_linear_14_98%22%20x1%3D%221.12691e-06%22%20y1%3D%22-1.03824%22%20x2%3D%2251.4922%22%20y2%3D%2256.1372%22%20gradientUnits%3D%22userSpaceOnUse%22%3E%20%3Cstop%20stop-color%3D%22%23E890E7%22%2F%3E%20%3Cstop%20offset%3D%220.489583%22%20stop-color%3D%22%23A070DE%22%2F%3E%20%3Cstop%20offset%3D%221%22%20stop-color%3D%22%2387B5F1%22%2F%3E%20%3C%2FlinearGradient%3E%20%3ClinearGradient%20id%3D%22paint1_linear_14_98%22%20x1%3D%220%22%20y1%3D%2224.9176%22%20x2%3D%2265.2359%22%20y2%3D%2224.9176%22%20gradientUnits%3D%22userSpaceOnUse%22%3E%20%3Cstop%20stop-color%3D%22%23F368E0%22%2F%3E%20%3Cstop%20offset%3D%220.494792%22%20stop-color%3D%22%235F27CD%22%2F%3E%20%3Cstop%20offset%3D%221%22%20stop-color%3D%22%2300D2D3%22%2F%3E%20%3C%2FlinearGradient%3E%20%3ClinearGradient%20id%3D%22paint2_linear_14_98%22%20x1%3D%22-6.10509%22%20y1%3D%22-17.1853%22%20x2%3D%2239.1694%22%20y2%3D%2233.083%22%20gradientUnits%3D%22userSpaceOnUse%22%3E%20%3Cstop%20stop-color%3D%22%23E890E7%22%2F%3E%20%3Cstop%20offset%3D%220.4896%22%20stop-color%3D%22%23A070DE%22%2F%3E%20%3Cstop%20offset%3D%221%22%20stop-color%3D%22%2387B5F1%22%2F%3E%20%3C%2FlinearGradient%3E%20%3ClinearGradient%20id%3D%22paint3_linear_14_98%22%20x1%3D%226.9812%22%20y1%3D%22-0.371039%22%20x2%3D%2246.1231%22%20y2%3D%2237.9181%22%20gradientUnits%3D%22userSpaceOnUse%22%3E%20%3Cstop%20stop-color%3D%22%23E890E7%22%2F%3E%20%3Cstop%20offset%3D%220.489583%22%20stop-color%3D%22%23A070DE%22%2F%3E%20%3Cstop%20offset%3D%221%22%20stop-color%3D%22%2387B5F1%22%2F%3E%20%3C%2FlinearGradient%3E%20%3ClinearGradient%20id%3D%22paint4_linear_14_98%22%20x1%3D%226.9812%22%20y1%3D%2219.2565%22%20x2%3D%2250.4405%22%20y2%3D%2219.2565%22%20gradientUnits%3D%22userSpaceOnUse%22%3E%20%3Cstop%20stop-color%3D%22%23F368E0%22%2F%3E%20%3Cstop%20offset%3D%220.494792%22%20stop-color%3D%22%235F27CD%22%2F%3E%20%3Cstop%20offset%3D%221%22%20stop-color%3D%22%2300D2D3%22%2F%3E%20%3C%2FlinearGradient%3E%20%3CclipPath%20id%3D%22clip0_14_98%22%3E%20%3Crect%20width%3D%2265.2359%22%20height%3D%2249.8373%22%20fill%3D%22white%22%2F%3E%20%3C%2FclipPath%3E%20%3C%2Fdefs%3E%20%3C%2Fsvg%3E";
@@ -149,6 +606,29 @@ enum StorageKey {
     TokensBySeriesInner { token_series: String },
     TokensPerOwner { account_hash: Vec<u8> },
     MarketDataTransactionFee,
+    UpgradeInputsBySeries,
+    ResaleCountByToken,
+    SeriesIdByIdempotencyKey,
+    StorageDeposits,
+    TrustedContracts,
+    LockerByToken,
+    TokenRoyaltyOverride,
+    VolumeBySeries,
+    PendingPayouts,
+    MintedPerOwner,
+    Holders,
+    TransferredInRevertWindow,
+    OwnershipChanges,
+    AllowlistBySeries,
+    AllowlistBySeriesInner { token_series: String },
+    CreatorTotals,
+    BatchMintCursors,
+    LastMintTime,
+    VestingBalances,
+    CountByOwnerSeries,
+    SoldBySeries,
+    BurnedBySeries,
+    CreatorMinted,
 }
 
 #[near_bindgen]
@@ -190,7 +670,8 @@ impl Contract {
             ),
             token_series_by_id: UnorderedMap::new(StorageKey::TokenSeriesById),
             metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
-            treasury_id: treasury_id.to_string(),
+            primary_treasury_id: treasury_id.to_string(),
+            secondary_treasury_id: treasury_id.to_string(),
             transaction_fee: TransactionFee {
                 next_fee: None,
                 start_time: None,
@@ -199,32 +680,214 @@ impl Contract {
             market_data_transaction_fee: MarketDataTransactionFee{
                 transaction_fee: UnorderedMap::new(StorageKey::MarketDataTransactionFee)
             },
+            enforce_metadata_hashes: false,
+            auto_approve_market: None,
+            upgrade_inputs_by_series: LookupMap::new(StorageKey::UpgradeInputsBySeries),
+            resale_count_by_token: LookupMap::new(StorageKey::ResaleCountByToken),
+            series_id_by_idempotency_key: UnorderedMap::new(StorageKey::SeriesIdByIdempotencyKey),
+            metadata_limits: MetadataLimits::default(),
+            total_minted: 0,
+            total_burned: 0,
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            trusted_contracts: UnorderedSet::new(StorageKey::TrustedContracts),
+            locker_by_token: LookupMap::new(StorageKey::LockerByToken),
+            max_copies_per_series: None,
+            token_royalty_override: LookupMap::new(StorageKey::TokenRoyaltyOverride),
+            volume_by_series: LookupMap::new(StorageKey::VolumeBySeries),
+            sold_by_series: LookupMap::new(StorageKey::SoldBySeries),
+            burned_by_series: LookupMap::new(StorageKey::BurnedBySeries),
+            pending_payouts: LookupMap::new(StorageKey::PendingPayouts),
+            creator_totals: LookupMap::new(StorageKey::CreatorTotals),
+            allowed_media_types: Vec::new(),
+            batch_mint_cursors: LookupMap::new(StorageKey::BatchMintCursors),
+            mint_cooldown_secs: None,
+            last_mint_time: LookupMap::new(StorageKey::LastMintTime),
+            vesting: None,
+            vesting_balances: LookupMap::new(StorageKey::VestingBalances),
+            min_treasury_cut: 0,
+            count_by_owner_series: LookupMap::new(StorageKey::CountByOwnerSeries),
+            owner_series_backfill_cursor: None,
+            owner_series_backfill_done: true,
+            next_series_id: 1,
+            retirement_available_at: None,
+            retired: false,
+            series_to_migrate: 0,
+            series_migrated: 0,
+            prepend_base_uri: false,
+            minted_per_owner: LookupMap::new(StorageKey::MintedPerOwner),
+            non_mintable_grace_period: DEFAULT_NON_MINTABLE_GRACE_PERIOD,
+            max_approvals_per_token: DEFAULT_MAX_APPROVALS_PER_TOKEN,
+            require_creator_royalty: false,
+            strict_royalty_accounts: false,
+            withdrawals_paused: false,
+            holders: LookupSet::new(StorageKey::Holders),
+            distinct_owners: 0,
+            max_distinct_owners: None,
+            secondary_treasury_bps: 0,
+            transferred_in_revert_window: LookupSet::new(StorageKey::TransferredInRevertWindow),
+            creator_minted: LookupMap::new(StorageKey::CreatorMinted),
+            ownership_changes: Vector::new(StorageKey::OwnershipChanges),
+            ownership_changes_total: 0,
+            last_buy_storage_usage: DEFAULT_BUY_STORAGE_USAGE,
+            allowlist_by_series: LookupMap::new(StorageKey::AllowlistBySeries),
         }
     }
 
+    /// Upgrades the released ContractV2 state, or a ContractV1 state that never ran the
+    /// previous migration. Any other layout is rejected instead of being misread.
     #[init(ignore_state)]
     pub fn migrate() -> Self {
-        let prev: ContractV1 = env::state_read().expect("ERR_NOT_INITIALIZED");
+        let state = env::storage_read(b"STATE").expect("ERR_NOT_INITIALIZED");
+        // a V1 state is too short to parse as V2 and V2 has trailing bytes as V1
+        let prev = match ContractV2::try_from_slice(&state) {
+            Ok(prev) => prev,
+            Err(_) => {
+                let prev = ContractV1::try_from_slice(&state).expect("Unknown contract state layout");
+                ContractV2 {
+                    tokens: prev.tokens,
+                    metadata: prev.metadata,
+                    token_series_by_id: prev.token_series_by_id,
+                    treasury_id: prev.treasury_id,
+                    transaction_fee: prev.transaction_fee,
+                    market_data_transaction_fee: MarketDataTransactionFee{
+                        transaction_fee: UnorderedMap::new(StorageKey::MarketDataTransactionFee)
+                    },
+                }
+            }
+        };
         assert_eq!(
             env::predecessor_account_id(),
             prev.tokens.owner_id,
             "Only owner"
         );
 
+        let next_series_id = prev.token_series_by_id
+            .keys()
+            .filter_map(|token_series_id| token_series_id.parse::<u64>().ok())
+            .max()
+            .unwrap_or(0) + 1;
+        let series_to_migrate = prev.token_series_by_id.len();
+        // same storage, the entries are rewritten to TokenSeries by migrate_series
+        let token_series_by_id: UnorderedMap<TokenSeriesId, TokenSeries> =
+            UnorderedMap::try_from_slice(&prev.token_series_by_id.try_to_vec().unwrap()).unwrap();
+        let total_minted = prev.tokens.owner_by_id.len() as u128;
+
         let this = Contract {
             tokens: prev.tokens,
             metadata: prev.metadata,
-            token_series_by_id: prev.token_series_by_id,
-            treasury_id: prev.treasury_id,
+            token_series_by_id,
+            primary_treasury_id: prev.treasury_id.clone(),
+            secondary_treasury_id: prev.treasury_id,
             transaction_fee: prev.transaction_fee,
-            market_data_transaction_fee: MarketDataTransactionFee{
-                transaction_fee: UnorderedMap::new(StorageKey::MarketDataTransactionFee)
-            },
+            market_data_transaction_fee: prev.market_data_transaction_fee,
+            enforce_metadata_hashes: false,
+            auto_approve_market: None,
+            upgrade_inputs_by_series: LookupMap::new(StorageKey::UpgradeInputsBySeries),
+            resale_count_by_token: LookupMap::new(StorageKey::ResaleCountByToken),
+            series_id_by_idempotency_key: UnorderedMap::new(StorageKey::SeriesIdByIdempotencyKey),
+            metadata_limits: MetadataLimits::default(),
+            // burns before the upgrade were not tracked, so minted starts at the live supply
+            total_minted,
+            total_burned: 0,
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            trusted_contracts: UnorderedSet::new(StorageKey::TrustedContracts),
+            locker_by_token: LookupMap::new(StorageKey::LockerByToken),
+            max_copies_per_series: None,
+            token_royalty_override: LookupMap::new(StorageKey::TokenRoyaltyOverride),
+            volume_by_series: LookupMap::new(StorageKey::VolumeBySeries),
+            sold_by_series: LookupMap::new(StorageKey::SoldBySeries),
+            burned_by_series: LookupMap::new(StorageKey::BurnedBySeries),
+            pending_payouts: LookupMap::new(StorageKey::PendingPayouts),
+            creator_totals: LookupMap::new(StorageKey::CreatorTotals),
+            allowed_media_types: Vec::new(),
+            batch_mint_cursors: LookupMap::new(StorageKey::BatchMintCursors),
+            mint_cooldown_secs: None,
+            last_mint_time: LookupMap::new(StorageKey::LastMintTime),
+            vesting: None,
+            vesting_balances: LookupMap::new(StorageKey::VestingBalances),
+            min_treasury_cut: 0,
+            count_by_owner_series: LookupMap::new(StorageKey::CountByOwnerSeries),
+            owner_series_backfill_cursor: None,
+            owner_series_backfill_done: false,
+            next_series_id,
+            retirement_available_at: None,
+            retired: false,
+            series_to_migrate,
+            series_migrated: 0,
+            prepend_base_uri: false,
+            minted_per_owner: LookupMap::new(StorageKey::MintedPerOwner),
+            non_mintable_grace_period: DEFAULT_NON_MINTABLE_GRACE_PERIOD,
+            max_approvals_per_token: DEFAULT_MAX_APPROVALS_PER_TOKEN,
+            require_creator_royalty: false,
+            strict_royalty_accounts: false,
+            withdrawals_paused: false,
+            holders: LookupSet::new(StorageKey::Holders),
+            distinct_owners: 0,
+            max_distinct_owners: None,
+            secondary_treasury_bps: 0,
+            transferred_in_revert_window: LookupSet::new(StorageKey::TransferredInRevertWindow),
+            creator_minted: LookupMap::new(StorageKey::CreatorMinted),
+            ownership_changes: Vector::new(StorageKey::OwnershipChanges),
+            ownership_changes_total: 0,
+            last_buy_storage_usage: DEFAULT_BUY_STORAGE_USAGE,
+            allowlist_by_series: LookupMap::new(StorageKey::AllowlistBySeries),
         };
 
         this
     }
 
+    /// Rewrites up to limit series left in the released layout after migrate, returns how many
+    /// remain. Reading a series that is not rewritten yet panics, call until it returns 0.
+    pub fn migrate_series(&mut self, limit: u64) -> U64 {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        // a handle on the map values that can read and write them as raw bytes
+        let mut values: Vector<TokenSeries> =
+            Vector::try_from_slice(&self.token_series_by_id.values_as_vector().try_to_vec().unwrap()).unwrap();
+        let end = std::cmp::min(self.series_migrated.saturating_add(limit), self.series_to_migrate);
+        for index in self.series_migrated..end {
+            let prev = TokenSeriesV1::try_from_slice(&values.get_raw(index).unwrap())
+                .expect("Series already migrated");
+            let token_series = TokenSeries::from(prev);
+            values.replace_raw(index, &token_series.try_to_vec().unwrap());
+        }
+        self.series_migrated = end;
+        U64(self.series_to_migrate - end)
+    }
+
+    /// Counts up to limit tokens held before migrate into count_by_owner_series, in token id order.
+    /// Returns true once every token is counted, nft_supply_for_owner_by_series scans until then.
+    pub fn backfill_owner_series_counts(&mut self, limit: u64) -> bool {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        if self.owner_series_backfill_done {
+            return true;
+        }
+
+        let tokens: Vec<(TokenId, AccountId)> = match &self.owner_series_backfill_cursor {
+            Some(cursor) => self.tokens.owner_by_id.iter_from(cursor.clone()).take(limit as usize).collect(),
+            None => self.tokens.owner_by_id.iter().take(limit as usize).collect(),
+        };
+        for (token_id, owner_id) in tokens.iter() {
+            let token_series_id: TokenSeriesId = token_id.split(TOKEN_DELIMETER).next().unwrap().to_string();
+            let key = (owner_id.clone(), token_series_id);
+            let count = self.count_by_owner_series.get(&key).unwrap_or(0) + 1;
+            self.count_by_owner_series.insert(&key, &count);
+        }
+
+        if let Some((token_id, _)) = tokens.last() {
+            self.owner_series_backfill_cursor = Some(token_id.clone());
+        }
+        self.owner_series_backfill_done = (tokens.len() as u64) < limit;
+        self.owner_series_backfill_done
+    }
+
     #[payable]
     pub fn set_transaction_fee(&mut self, next_fee: u16, start_time: Option<TimestampSec>) {
         assert_one_yocto();
@@ -255,7 +918,7 @@ impl Contract {
         }
     }
 
-    pub fn calculate_market_data_transaction_fee(&mut self, token_series_id: &TokenSeriesId) -> u128{
+    pub fn calculate_market_data_transaction_fee(&self, token_series_id: &TokenSeriesId) -> u128{
         if let Some(transaction_fee) = self.market_data_transaction_fee.transaction_fee.get(&token_series_id){
             return transaction_fee;
         }
@@ -265,1040 +928,9657 @@ impl Contract {
     }
 
 
-    pub fn calculate_current_transaction_fee(&mut self) -> u128 {
+    pub fn calculate_current_transaction_fee(&self) -> u128 {
+        self.resolved_current_fee() as u128
+    }
+
+    /// Current fee with a due scheduled fee applied, without writing it
+    pub fn resolved_current_fee(&self) -> u16 {
         let transaction_fee: &TransactionFee = &self.transaction_fee;
-        if transaction_fee.next_fee.is_some() {
-            if to_sec(env::block_timestamp()) >= transaction_fee.start_time.unwrap() {
-                self.transaction_fee.current_fee = transaction_fee.next_fee.unwrap();
-                self.transaction_fee.next_fee = None;
-                self.transaction_fee.start_time = None;
-            }
+        match (transaction_fee.next_fee, transaction_fee.start_time) {
+            (Some(next_fee), Some(start_time)) if to_sec(env::block_timestamp()) >= start_time => next_fee,
+            _ => transaction_fee.current_fee,
+        }
+    }
+
+    /// Writes a due scheduled fee as the current fee, called by methods that change state
+    fn commit_fee_if_due(&mut self) {
+        let is_due = self.transaction_fee.start_time
+            .map(|start_time| to_sec(env::block_timestamp()) >= start_time)
+            .unwrap_or(false);
+        if let (true, Some(next_fee)) = (is_due, self.transaction_fee.next_fee) {
+            self.transaction_fee.current_fee = next_fee;
+            self.transaction_fee.next_fee = None;
+            self.transaction_fee.start_time = None;
         }
-        self.transaction_fee.current_fee as u128
     }
 
     pub fn get_transaction_fee(&self) -> &TransactionFee {
         &self.transaction_fee
     }
 
-    pub fn get_market_data_transaction_fee (&self, token_series_id: &TokenId) -> u128{
-        if let Some(transaction_fee) = self.market_data_transaction_fee.transaction_fee.get(&token_series_id){
-            return transaction_fee;
+    /// Owner, treasury and fee schedule in one read, a due fee change is reported as current
+    pub fn contract_status(&self) -> ContractStatus {
+        let transaction_fee = &self.transaction_fee;
+        let is_due = transaction_fee.start_time
+            .map(|start_time| to_sec(env::block_timestamp()) >= start_time)
+            .unwrap_or(false);
+
+        let (current_fee, scheduled_fee, scheduled_fee_start_time) = match transaction_fee.next_fee {
+            Some(next_fee) if is_due => (next_fee, None, None),
+            next_fee => (transaction_fee.current_fee, next_fee, transaction_fee.start_time),
+        };
+
+        ContractStatus {
+            owner_id: self.tokens.owner_id.clone(),
+            treasury_id: self.primary_treasury_id.clone(),
+            secondary_treasury_id: self.secondary_treasury_id.clone(),
+            current_fee,
+            scheduled_fee,
+            scheduled_fee_start_time,
         }
-        // fallback to default transaction fee
-        self.transaction_fee.current_fee as u128
     }
 
+    /// Stored fee schedule as is, effective_now applies a due scheduled fee without writing it
+    pub fn fee_schedule(&self) -> FeeSchedule {
+        FeeSchedule {
+            current: self.transaction_fee.current_fee,
+            next: self.transaction_fee.next_fee,
+            starts_at: self.transaction_fee.start_time,
+            effective_now: self.resolved_current_fee(),
+        }
+    }
 
-    // Treasury
+    /// Rewrites per-series fee snapshots to the current fee, paginated to bound gas
     #[payable]
-    pub fn set_treasury(&mut self, treasury_id: ValidAccountId) {
+    pub fn refresh_all_series_fees(&mut self, from_index: Option<U128>, limit: Option<u64>) -> u64 {
         assert_one_yocto();
         assert_eq!(
             env::predecessor_account_id(),
             self.tokens.owner_id,
             "Owner only"
         );
-        self.treasury_id = treasury_id.to_string();
-    }
-
-    // CUSTOM
-
-    #[payable]
-    pub fn nft_create_series(
-        &mut self,
-        creator_id: Option<ValidAccountId>,
-        token_metadata: TokenMetadata,
-        price: Option<U128>,
-        royalty: Option<HashMap<AccountId, u32>>,
-    ) -> TokenSeriesJson {
-        let initial_storage_usage = env::storage_usage();
-        let caller_id = env::predecessor_account_id();
-
-        if creator_id.is_some() {
-            assert_eq!(creator_id.unwrap().to_string(), caller_id, "Caller is not creator_id");
-        }
-
-        let token_series_id = format!("{}", (self.token_series_by_id.len() + 1));
-
-        assert!(
-            self.token_series_by_id.get(&token_series_id).is_none(),
-            "Duplicate token_series_id"
-        );
-
-        let title = token_metadata.title.clone();
-        assert!(title.is_some(), "Token_metadata.title is required");
-        
-
-        let mut total_perpetual = 0;
-        let mut total_accounts = 0;
-        let royalty_res: HashMap<AccountId, u32> = if let Some(royalty) = royalty {
-            for (k , v) in royalty.iter() {
-                if !is_valid_account_id(k.as_bytes()) {
-                    env::panic("Not valid account_id for royalty".as_bytes());
-                };
-                total_perpetual += *v;
-                total_accounts += 1;
-            }
-            royalty
-        } else {
-            HashMap::new()
-        };
-
-        assert!(total_accounts <= 10, "Royalty exceeds 10 accounts");
 
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
         assert!(
-            total_perpetual <= 5000,
-            "Exceeds maximum royalty -> 50%",
+            (self.token_series_by_id.len() as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
         );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
 
-        let price_res: Option<u128> = if price.is_some() {
-            assert!(
-                price.unwrap().0 < MAX_PRICE,
-                "Price is higher than {}",
-                MAX_PRICE
-            );
-            Some(price.unwrap().0)
-        } else {
-            None
-        };
-
-        self.token_series_by_id.insert(&token_series_id, &TokenSeries{
-            metadata: token_metadata.clone(),
-            creator_id: caller_id.to_string(),
-            tokens: UnorderedSet::new(
-                StorageKey::TokensBySeriesInner {
-                    token_series: token_series_id.clone(),
-                }
-                .try_to_vec()
-                .unwrap(),
-            ),
-            price: price_res,
-            is_mintable: true,
-            royalty: royalty_res.clone(),
-        });
-
-        // set market data transaction fee
+        self.commit_fee_if_due();
         let current_transaction_fee = self.calculate_current_transaction_fee();
-        self.market_data_transaction_fee.transaction_fee.insert(&token_series_id, &current_transaction_fee);
+        let token_series_ids: Vec<TokenSeriesId> = self.token_series_by_id
+            .keys()
+            .skip(start_index as usize)
+            .take(limit)
+            .collect();
+
+        for token_series_id in token_series_ids.iter() {
+            self.market_data_transaction_fee.transaction_fee.insert(token_series_id, &current_transaction_fee);
+        }
 
         env::log(
             json!({
-                "type": "nft_create_series",
+                "type": "refresh_all_series_fees",
                 "params": {
-                    "token_series_id": token_series_id,
-                    "token_metadata": token_metadata,
-                    "creator_id": caller_id,
-                    "price": price,
-                    "royalty": royalty_res,
-                    "transaction_fee": &current_transaction_fee.to_string()
+                    "from_index": start_index.to_string(),
+                    "count": token_series_ids.len(),
+                    "transaction_fee": current_transaction_fee.to_string()
                 }
             })
             .to_string()
             .as_bytes(),
         );
 
-        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
+        token_series_ids.len() as u64
+    }
 
-		TokenSeriesJson{
-            token_series_id,
-			metadata: token_metadata,
-			creator_id: caller_id.into(),
-            royalty: royalty_res,
-            transaction_fee: Some(current_transaction_fee.into()) 
-		}
+    pub fn get_market_data_transaction_fee (&self, token_series_id: &TokenId) -> u128{
+        if let Some(transaction_fee) = self.market_data_transaction_fee.transaction_fee.get(&token_series_id){
+            return transaction_fee;
+        }
+        // fallback to default transaction fee
+        self.transaction_fee.current_fee as u128
     }
 
-    #[payable]
-    pub fn nft_buy(
-        &mut self, 
-        token_series_id: TokenSeriesId, 
-        receiver_id: ValidAccountId
-    ) -> TokenId {
-        let initial_storage_usage = env::storage_usage();
+    /// Fee locked for the series, None when the series falls back to the current fee
+    pub fn nft_series_fee_snapshot(&self, token_series_id: TokenSeriesId) -> Option<u128> {
+        self.market_data_transaction_fee.transaction_fee.get(&token_series_id)
+    }
 
-        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
-        let price: u128 = token_series.price.expect("Not for sale.");
-        let attached_deposit = env::attached_deposit();
-        assert!(
-            attached_deposit >= price,
-            "Attached deposit is less than price : {}",
-            price
-        );
-        let token_id: TokenId = self._nft_mint_series(token_series_id.clone(), receiver_id.to_string());
-
-        let for_treasury = price as u128 * self.calculate_market_data_transaction_fee(&token_series_id) / 10_000u128;
-        let price_deducted = price - for_treasury;
-        Promise::new(token_series.creator_id).transfer(price_deducted);
-
-        if for_treasury != 0 {
-            Promise::new(self.treasury_id.clone()).transfer(for_treasury);
-        }
-
-        refund_deposit(env::storage_usage() - initial_storage_usage, price);
 
-        NearEvent::log_nft_mint(
-            receiver_id.to_string(),
-            vec![token_id.clone()],
-            Some(json!({"price": price.to_string()}).to_string())
+    // Treasury, sets both the primary and the secondary treasury
+    #[payable]
+    pub fn set_treasury(&mut self, treasury_id: ValidAccountId) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
         );
-
-        token_id
+        self.primary_treasury_id = treasury_id.to_string();
+        self.secondary_treasury_id = treasury_id.to_string();
     }
 
+    // Primary treasury, receives the nft_buy fee
     #[payable]
-    pub fn nft_mint(
-        &mut self, 
-        token_series_id: TokenSeriesId, 
-        receiver_id: ValidAccountId
-    ) -> TokenId {
-        let initial_storage_usage = env::storage_usage();
-
-        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist.");
-        assert_eq!(env::predecessor_account_id(), token_series.creator_id, "Not a creator.");
-        let token_id: TokenId = self._nft_mint_series(token_series_id, receiver_id.to_string());
-
-        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
-
-        NearEvent::log_nft_mint(
-            receiver_id.to_string(),
-            vec![token_id.clone()],
-            None,
+    pub fn set_primary_treasury(&mut self, treasury_id: ValidAccountId) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
         );
+        self.primary_treasury_id = treasury_id.to_string();
+    }
 
-        token_id
+    pub fn get_primary_treasury(&self) -> AccountId {
+        self.primary_treasury_id.clone()
     }
 
+    // Secondary treasury, receives the secondary_treasury_bps cut of resale payouts
     #[payable]
-    pub fn nft_mint_and_approve(
-        &mut self, 
-        token_series_id: TokenSeriesId, 
-        account_id: ValidAccountId,
-        msg: Option<String>,
-    ) -> Option<Promise> {
-        let initial_storage_usage = env::storage_usage();
-
-        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
-        assert_eq!(env::predecessor_account_id(), token_series.creator_id, "Not a creator");
-        let token_id: TokenId = self._nft_mint_series(token_series_id, token_series.creator_id.clone());
-
-        // Need to copy the nft_approve code here to solve the gas problem
-        // get contract-level LookupMap of token_id to approvals HashMap
-        let approvals_by_id = self.tokens.approvals_by_id.as_mut().unwrap();
-
-        // update HashMap of approvals for this token
-        let approved_account_ids =
-            &mut approvals_by_id.get(&token_id).unwrap_or_else(|| HashMap::new());
-        let account_id: AccountId = account_id.into();
-        let approval_id: u64 =
-            self.tokens.next_approval_id_by_id.as_ref().unwrap().get(&token_id).unwrap_or_else(|| 1u64);
-        approved_account_ids.insert(account_id.clone(), approval_id);
+    pub fn set_secondary_treasury(&mut self, treasury_id: ValidAccountId) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        self.secondary_treasury_id = treasury_id.to_string();
+    }
 
-        // save updated approvals HashMap to contract's LookupMap
-        approvals_by_id.insert(&token_id, &approved_account_ids);
+    pub fn get_secondary_treasury(&self) -> AccountId {
+        self.secondary_treasury_id.clone()
+    }
 
-        // increment next_approval_id for this token
-        self.tokens.next_approval_id_by_id.as_mut().unwrap().insert(&token_id, &(approval_id + 1));
+    // Metadata hashes
+    #[payable]
+    pub fn set_enforce_metadata_hashes(&mut self, enforce_metadata_hashes: bool) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        self.enforce_metadata_hashes = enforce_metadata_hashes;
+    }
 
-        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
+    pub fn get_enforce_metadata_hashes(&self) -> bool {
+        self.enforce_metadata_hashes
+    }
 
-        NearEvent::log_nft_mint(
-            token_series.creator_id.clone(),
-            vec![token_id.clone()],
-            None,
+    #[payable]
+    pub fn set_metadata_limits(&mut self, metadata_limits: MetadataLimits) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
         );
+        self.metadata_limits = metadata_limits;
+    }
 
-        if let Some(msg) = msg {
-            Some(ext_approval_receiver::nft_on_approve(
-                token_id,
-                token_series.creator_id,
-                approval_id,
-                msg,
-                &account_id,
-                NO_DEPOSIT,
-                env::prepaid_gas() - GAS_FOR_NFT_APPROVE - GAS_FOR_MINT,
-            ))
-        } else {
-            None
-        }
+    pub fn get_metadata_limits(&self) -> &MetadataLimits {
+        &self.metadata_limits
     }
 
-    fn _nft_mint_series(
-        &mut self, 
-        token_series_id: TokenSeriesId, 
-        receiver_id: AccountId
-    ) -> TokenId {
-        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series does not exist");
-        assert!(
-            token_series.is_mintable,
-            "Token series is not mintable"
+    // nft_token returns media and reference prefixed with the contract base_uri
+    #[payable]
+    pub fn set_prepend_base_uri(&mut self, prepend_base_uri: bool) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
         );
+        self.prepend_base_uri = prepend_base_uri;
+    }
 
-        let num_tokens = token_series.tokens.len();
-        let max_copies = token_series.metadata.copies.unwrap_or(u64::MAX);
-        assert!(num_tokens < max_copies, "Series supply maxed");
+    pub fn get_prepend_base_uri(&self) -> bool {
+        self.prepend_base_uri
+    }
 
-        if (num_tokens + 1) >= max_copies {
-            token_series.is_mintable = false;
-        }
+    // Mints and transfers to an account that never held a token fail once the cap is reached
+    #[payable]
+    pub fn set_max_distinct_owners(&mut self, max_distinct_owners: Option<u64>) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        self.max_distinct_owners = max_distinct_owners;
+    }
 
-        let token_id = format!("{}{}{}", &token_series_id, TOKEN_DELIMETER, num_tokens + 1);
-        token_series.tokens.insert(&token_id);
-        self.token_series_by_id.insert(&token_series_id, &token_series);
+    pub fn get_max_distinct_owners(&self) -> Option<u64> {
+        self.max_distinct_owners
+    }
 
-        // you can add custom metadata to each token here
-        let metadata = Some(TokenMetadata {
-            title: None,          // ex. "Arch Nemesis: Mail Carrier" or "Parcel #5055"
-            description: None,    // free-form description
-            media: None, // URL to associated media, preferably to decentralized, content-addressed storage
-            media_hash: None, // Base64-encoded sha256 hash of content referenced by the `media` field. Required if `media` is included.
-            copies: None, // number of copies of this set of metadata in existence when token was minted.
-            issued_at: Some(env::block_timestamp().to_string()), // ISO 8601 datetime when token was issued or minted
-            expires_at: None, // ISO 8601 datetime when token expires
-            starts_at: None, // ISO 8601 datetime when token starts being valid
-            updated_at: None, // ISO 8601 datetime when token was last updated
-            extra: None, // anything extra the NFT wants to store on-chain. Can be stringified JSON.
-            reference: None, // URL to an off-chain JSON file with more info.
-            reference_hash: None, // Base64-encoded sha256 hash of JSON from reference field. Required if `reference` is included.
-        });
+    pub fn get_distinct_owners(&self) -> U64 {
+        self.distinct_owners.into()
+    }
 
-        //let token = self.tokens.mint(token_id, receiver_id, metadata);
-        // From : https://github.com/near/near-sdk-rs/blob/master/near-contract-standards/src/non_fungible_token/core/core_impl.rs#L359
-        // This allows lazy minting
+    // Treasury cut added as a royalty recipient in nft_payout and nft_transfer_payout
+    #[payable]
+    pub fn set_secondary_treasury_bps(&mut self, secondary_treasury_bps: u32) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        // series royalty is capped at MAX_ROYALTY_BPS so the payout total stays within 10000
+        assert!(secondary_treasury_bps <= 5000, "Secondary treasury bps exceeds 5000");
+        self.secondary_treasury_bps = secondary_treasury_bps;
+    }
 
-        let owner_id: AccountId = receiver_id;
-        self.tokens.owner_by_id.insert(&token_id, &owner_id);
+    pub fn get_secondary_treasury_bps(&self) -> u32 {
+        self.secondary_treasury_bps
+    }
 
-        self.tokens
-            .token_metadata_by_id
-            .as_mut()
-            .and_then(|by_id| by_id.insert(&token_id, &metadata.as_ref().unwrap()));
+    // nft_create_series rejects royalty maps without the creator
+    #[payable]
+    pub fn set_require_creator_royalty(&mut self, require_creator_royalty: bool) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        self.require_creator_royalty = require_creator_royalty;
+    }
 
-         if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
-             let mut token_ids = tokens_per_owner.get(&owner_id).unwrap_or_else(|| {
-                 UnorderedSet::new(StorageKey::TokensPerOwner {
-                     account_hash: env::sha256(&owner_id.as_bytes()),
-                 })
-             });
-             token_ids.insert(&token_id);
-             tokens_per_owner.insert(&owner_id, &token_ids);
-         }
+    pub fn get_require_creator_royalty(&self) -> bool {
+        self.require_creator_royalty
+    }
 
+    // Royalty recipients set by creators must be named accounts
+    #[payable]
+    pub fn set_strict_royalty_accounts(&mut self, strict_royalty_accounts: bool) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        self.strict_royalty_accounts = strict_royalty_accounts;
+    }
 
-        token_id
+    pub fn get_strict_royalty_accounts(&self) -> bool {
+        self.strict_royalty_accounts
     }
 
+    // Minimum time between two nft_buy mints to the same receiver, None disables it
     #[payable]
-    pub fn nft_set_series_non_mintable(&mut self, token_series_id: TokenSeriesId) {
+    pub fn set_mint_cooldown_secs(&mut self, mint_cooldown_secs: Option<u32>) {
         assert_one_yocto();
-
-        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
         assert_eq!(
             env::predecessor_account_id(),
-            token_series.creator_id,
-            "Creator only"
+            self.tokens.owner_id,
+            "Owner only"
         );
+        self.mint_cooldown_secs = mint_cooldown_secs;
+    }
 
+    pub fn get_mint_cooldown_secs(&self) -> Option<u32> {
+        self.mint_cooldown_secs
+    }
+
+    // Floor for the nft_buy treasury cut when the fee is non-zero, capped at the price
+    #[payable]
+    pub fn set_min_treasury_cut(&mut self, min_treasury_cut: U128) {
+        assert_one_yocto();
         assert_eq!(
-            token_series.is_mintable,
-            true,
-            "Already non-mintable"
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
         );
+        self.min_treasury_cut = min_treasury_cut.into();
+    }
+
+    pub fn get_min_treasury_cut(&self) -> U128 {
+        self.min_treasury_cut.into()
+    }
 
+    // Vesting of primary sale proceeds, applies to sales made while set
+    #[payable]
+    pub fn set_vesting(&mut self, vesting: Option<Vesting>) {
+        assert_one_yocto();
         assert_eq!(
-            token_series.metadata.copies,
-            None,
-            "Decrease supply if copies not null"
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
         );
+        if let Some(vesting) = &vesting {
+            assert!(vesting.duration > 0, "Vesting duration must be positive");
+            assert!(vesting.cliff <= vesting.duration, "Vesting cliff exceeds duration");
+        }
+        self.vesting = vesting;
+    }
 
-        token_series.is_mintable = false;
-        self.token_series_by_id.insert(&token_series_id, &token_series);
-        env::log(
-            json!({
-                "type": "nft_set_series_non_mintable",
-                "params": {
-                    "token_series_id": token_series_id,
-                }
-            })
-            .to_string()
-            .as_bytes(),
-        );
+    pub fn get_vesting(&self) -> Option<Vesting> {
+        self.vesting
     }
 
+    // Media types accepted by nft_create_series, an empty list accepts any
     #[payable]
-    pub fn nft_decrease_series_copies(
-        &mut self, 
-        token_series_id: TokenSeriesId, 
-        decrease_copies: U64
-    ) -> U64 {
+    pub fn set_allowed_media_types(&mut self, allowed_media_types: Vec<String>) {
         assert_one_yocto();
-
-        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
         assert_eq!(
             env::predecessor_account_id(),
-            token_series.creator_id,
-            "Creator only"
+            self.tokens.owner_id,
+            "Owner only"
         );
+        self.allowed_media_types = allowed_media_types;
+    }
 
-        let minted_copies = token_series.tokens.len();
-        let copies = token_series.metadata.copies.unwrap();
+    pub fn get_allowed_media_types(&self) -> Vec<String> {
+        self.allowed_media_types.clone()
+    }
 
-        assert!(
-            (copies - decrease_copies.0) >= minted_copies,
-            "Cannot decrease supply, already minted: {}", minted_copies
+    // Delay between nft_request_non_mintable and the series becoming non-mintable
+    #[payable]
+    pub fn set_non_mintable_grace_period(&mut self, grace_period: TimestampSec) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
         );
+        self.non_mintable_grace_period = grace_period;
+    }
 
-        let is_non_mintable = if (copies - decrease_copies.0) == minted_copies {
-            token_series.is_mintable = false;
-            true
-        } else {
-            false
-        };
-
-        token_series.metadata.copies = Some(copies - decrease_copies.0);
+    pub fn get_non_mintable_grace_period(&self) -> TimestampSec {
+        self.non_mintable_grace_period
+    }
 
-        self.token_series_by_id.insert(&token_series_id, &token_series);
-        env::log(
-            json!({
-                "type": "nft_decrease_series_copies",
-                "params": {
-                    "token_series_id": token_series_id,
-                    "copies": U64::from(token_series.metadata.copies.unwrap()),
-                    "is_non_mintable": is_non_mintable,
-                }
-            })
-            .to_string()
-            .as_bytes(),
+    // Bounds approval storage per token, re-approving an approved account doesn't count
+    #[payable]
+    pub fn set_max_approvals_per_token(&mut self, max_approvals_per_token: u32) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
         );
-        U64::from(token_series.metadata.copies.unwrap())
+        self.max_approvals_per_token = max_approvals_per_token;
     }
 
+    pub fn get_max_approvals_per_token(&self) -> u32 {
+        self.max_approvals_per_token
+    }
+
+    // Default copies for series created without copies, None allows unlimited editions
     #[payable]
-    pub fn nft_set_series_price(&mut self, token_series_id: TokenSeriesId, price: Option<U128>) -> Option<U128> {
+    pub fn set_max_copies_per_series(&mut self, max_copies_per_series: Option<u64>) {
         assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        self.max_copies_per_series = max_copies_per_series;
+    }
 
-        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+    pub fn get_max_copies_per_series(&self) -> Option<u64> {
+        self.max_copies_per_series
+    }
+
+    // Marketplace approved on every mint
+    #[payable]
+    pub fn set_auto_approve_market(&mut self, market_id: Option<ValidAccountId>) {
+        assert_one_yocto();
         assert_eq!(
             env::predecessor_account_id(),
-            token_series.creator_id,
-            "Creator only"
+            self.tokens.owner_id,
+            "Owner only"
         );
+        self.auto_approve_market = market_id.map(|market_id| market_id.to_string());
+    }
+
+    pub fn get_auto_approve_market(&self) -> Option<AccountId> {
+        self.auto_approve_market.clone()
+    }
 
+    /// Rebuilds the account's tokens_per_owner set from owner_by_id.
+    /// The set is cleared when from_index is 0, page through owner_by_id to complete the repair.
+    #[payable]
+    pub fn repair_tokens_per_owner(
+        &mut self,
+        account_id: ValidAccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> U64 {
+        assert_one_yocto();
         assert_eq!(
-            token_series.is_mintable,
-            true,
-            "Token series is not mintable"
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
         );
 
-        if price.is_none() {
-            token_series.price = None;
-        } else {
-            assert!(
-                price.unwrap().0 < MAX_PRICE,
-                "Price is higher than {}",
-                MAX_PRICE
-            );
-            token_series.price = Some(price.unwrap().0);
-        }
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        assert!(
+            (self.tokens.owner_by_id.len() as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
 
-        self.token_series_by_id.insert(&token_series_id, &token_series);
+        let account_id: AccountId = account_id.into();
+        let tokens_per_owner = self.tokens.tokens_per_owner.as_mut().expect("Enumeration not enabled");
+        let mut token_ids = tokens_per_owner.get(&account_id).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::TokensPerOwner {
+                account_hash: env::sha256(account_id.as_bytes()),
+            })
+        });
+        let before = token_ids.len();
 
-        // set market data transaction fee
-        let current_transaction_fee = self.calculate_current_transaction_fee();
-        self.market_data_transaction_fee.transaction_fee.insert(&token_series_id, &current_transaction_fee);
+        if start_index == 0 {
+            token_ids.clear();
+        }
+        for (token_id, owner_id) in self.tokens.owner_by_id.iter().skip(start_index as usize).take(limit) {
+            if owner_id == account_id {
+                token_ids.insert(&token_id);
+            }
+        }
+        let after = token_ids.len();
+        tokens_per_owner.insert(&account_id, &token_ids);
 
         env::log(
             json!({
-                "type": "nft_set_series_price",
+                "type": "repair_tokens_per_owner",
                 "params": {
-                    "token_series_id": token_series_id,
-                    "price": price,
-                    "transaction_fee": current_transaction_fee.to_string()
+                    "account_id": account_id,
+                    "before": U64(before),
+                    "after": U64(after),
                 }
             })
             .to_string()
             .as_bytes(),
         );
-        return price;
+
+        U64(after)
     }
 
+    // Ecosystem contracts allowed to call privileged cross-contract methods
     #[payable]
-    pub fn nft_burn(&mut self, token_id: TokenId) {
+    pub fn add_trusted_contract(&mut self, account_id: ValidAccountId) {
         assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        self.trusted_contracts.insert(account_id.as_ref());
+    }
 
-        let owner_id = self.tokens.owner_by_id.get(&token_id).unwrap();
+    #[payable]
+    pub fn remove_trusted_contract(&mut self, account_id: ValidAccountId) {
+        assert_one_yocto();
         assert_eq!(
-            owner_id,
             env::predecessor_account_id(),
-            "Token owner only"
+            self.tokens.owner_id,
+            "Owner only"
         );
+        self.trusted_contracts.remove(account_id.as_ref());
+    }
 
-        if let Some(next_approval_id_by_id) = &mut self.tokens.next_approval_id_by_id {
-            next_approval_id_by_id.remove(&token_id);
-        }
+    pub fn is_trusted(&self, account_id: ValidAccountId) -> bool {
+        self.trusted_contracts.contains(account_id.as_ref())
+    }
 
-        if let Some(approvals_by_id) = &mut self.tokens.approvals_by_id {
-            approvals_by_id.remove(&token_id);
-        }
+    pub fn get_trusted_contracts(&self) -> Vec<AccountId> {
+        self.trusted_contracts.to_vec()
+    }
 
-        if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
-            let mut token_ids = tokens_per_owner.get(&owner_id).unwrap();
-            token_ids.remove(&token_id);
-            tokens_per_owner.insert(&owner_id, &token_ids);
-        }
+    fn require_trusted(&self) {
+        assert!(
+            self.trusted_contracts.contains(&env::predecessor_account_id()),
+            "Trusted contract only"
+        );
+    }
 
-        if let Some(token_metadata_by_id) = &mut self.tokens.token_metadata_by_id {
-            token_metadata_by_id.remove(&token_id);
-        }
+    /// Locks the token against transfers and burns until the same contract unlocks it
+    #[payable]
+    pub fn nft_lock(&mut self, token_id: TokenId) {
+        self.require_trusted();
+        let initial_storage_usage = env::storage_usage();
+        let locker_id = env::predecessor_account_id();
 
-        self.tokens.owner_by_id.remove(&token_id);
+        assert!(self.tokens.owner_by_id.get(&token_id).is_some(), "Token not found");
+        let is_approved = self.tokens.approvals_by_id
+            .as_ref()
+            .and_then(|by_id| by_id.get(&token_id))
+            .map(|approvals| approvals.contains_key(&locker_id))
+            .unwrap_or(false);
+        assert!(is_approved, "Locker is not approved for token");
+        assert!(self.locker_by_token.get(&token_id).is_none(), "Token is locked");
 
-        NearEvent::log_nft_burn(
-            owner_id,
-            vec![token_id],
-            None,
-            None,
+        self.locker_by_token.insert(&token_id, &locker_id);
+
+        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
+
+        env::log(
+            json!({
+                "type": "nft_lock",
+                "params": {
+                    "token_id": token_id,
+                    "locker_id": locker_id,
+                }
+            })
+            .to_string()
+            .as_bytes(),
         );
     }
 
-    // CUSTOM VIEWS
+    #[payable]
+    pub fn nft_unlock(&mut self, token_id: TokenId) {
+        assert_one_yocto();
 
-	pub fn nft_get_series_single(&self, token_series_id: TokenSeriesId) -> TokenSeriesJson {
-		let token_series = self.token_series_by_id.get(&token_series_id).expect("Series does not exist");
-        let current_transaction_fee = self.get_market_data_transaction_fee(&token_series_id);
-		TokenSeriesJson{
-            token_series_id,
-			metadata: token_series.metadata,
-			creator_id: token_series.creator_id,
-            royalty: token_series.royalty,
-            transaction_fee: Some(current_transaction_fee.into()) 
-		}
-	}
+        let locker_id = self.locker_by_token.get(&token_id).expect("Token is not locked");
+        assert_eq!(env::predecessor_account_id(), locker_id, "Locker only");
+        self.locker_by_token.remove(&token_id);
 
-    pub fn nft_get_series_format(self) -> (char, &'static str, &'static str) {
-        (TOKEN_DELIMETER, TITLE_DELIMETER, EDITION_DELIMETER)
+        env::log(
+            json!({
+                "type": "nft_unlock",
+                "params": {
+                    "token_id": token_id,
+                    "locker_id": locker_id,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
     }
 
-    pub fn nft_get_series_price(self, token_series_id: TokenSeriesId) -> Option<U128> {
-        let price = self.token_series_by_id.get(&token_series_id).unwrap().price;
-        match price {
-            Some(p) => return Some(U128::from(p)),
-            None => return None
-        };
+    pub fn nft_locker(&self, token_id: TokenId) -> Option<AccountId> {
+        self.locker_by_token.get(&token_id)
     }
 
-    pub fn nft_get_series(
-        &self,
-        from_index: Option<U128>,
-        limit: Option<u64>,
-    ) -> Vec<TokenSeriesJson> {
-        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
-        assert!(
-            (self.token_series_by_id.len() as u128) > start_index,
-            "Out of bounds, please use a smaller from_index."
-        );
-        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
-        assert_ne!(limit, 0, "Cannot provide limit of 0.");
-
-        self.token_series_by_id
-            .iter()
-            .skip(start_index as usize)
-            .take(limit)
-            .map(|(token_series_id, token_series)| TokenSeriesJson{
-                token_series_id,
-                metadata: token_series.metadata,
-                creator_id: token_series.creator_id,
-                royalty: token_series.royalty,
-                transaction_fee: None 
-            })
-            .collect()
+    fn _assert_not_locked(&self, token_id: &TokenId) {
+        assert!(self.locker_by_token.get(token_id).is_none(), "Token is locked");
     }
 
-    pub fn nft_supply_for_series(&self, token_series_id: TokenSeriesId) -> U64 {
-        self.token_series_by_id.get(&token_series_id).expect("Token series not exist").tokens.len().into()
-    }
+    // CUSTOM
 
-    pub fn nft_tokens_by_series(
-        &self,
-        token_series_id: TokenSeriesId,
-        from_index: Option<U128>,
-        limit: Option<u64>,
-    ) -> Vec<Token> {
-        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
-        let tokens = self.token_series_by_id.get(&token_series_id).unwrap().tokens;
-        assert!(
-            (tokens.len() as u128) > start_index,
-            "Out of bounds, please use a smaller from_index."
-        );
-        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
-        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+    #[payable]
+    pub fn nft_create_series(
+        &mut self,
+        creator_id: Option<ValidAccountId>,
+        token_metadata: TokenMetadata,
+        price: Option<U128>,
+        royalty: Option<HashMap<AccountId, u32>>,
+        idempotency_key: Option<String>,
+        media_type: Option<String>,
+    ) -> TokenSeriesJson {
+        let initial_storage_usage = env::storage_usage();
+        let caller_id = env::predecessor_account_id();
+        assert!(!self.retired, "Contract retired");
 
-        tokens
-            .iter()
-            .skip(start_index as usize)
-            .take(limit)
-            .map(|token_id| self.nft_token(token_id).unwrap())
-            .collect()
-    }
+        if creator_id.is_some() {
+            assert_eq!(creator_id.unwrap().to_string(), caller_id, "Caller is not creator_id");
+        }
 
-    pub fn nft_token(&self, token_id: TokenId) -> Option<Token> {
-        let owner_id = self.tokens.owner_by_id.get(&token_id)?;
-        let approved_account_ids = self
-            .tokens
-            .approvals_by_id
+        // keys are scoped per caller so relayers can't collide with each other
+        let idempotency_key = idempotency_key.map(|key| format!("{}{}{}", caller_id, TOKEN_DELIMETER, key));
+        if let Some(token_series_id) = idempotency_key
             .as_ref()
-            .and_then(|by_id| by_id.get(&token_id).or_else(|| Some(HashMap::new())));
+            .and_then(|key| self.series_id_by_idempotency_key.get(key))
+        {
+            refund_deposit(0, 0);
+            return self.nft_get_series_single(token_series_id);
+        }
 
-        // CUSTOM (switch metadata for the token_series metadata)
-        let mut token_id_iter = token_id.split(TOKEN_DELIMETER);
-        let token_series_id = token_id_iter.next().unwrap().parse().unwrap();
-        let series_metadata = self.token_series_by_id.get(&token_series_id).unwrap().metadata;
+        let token_series_id = format!("{}", self.next_series_id);
+        self.next_series_id += 1;
 
-        let mut token_metadata = self.tokens.token_metadata_by_id.as_ref().unwrap().get(&token_id).unwrap();
+        assert!(
+            self.token_series_by_id.get(&token_series_id).is_none(),
+            "Duplicate token_series_id"
+        );
 
-        token_metadata.title = series_metadata.title;
+        let title = token_metadata.title.clone();
+        assert!(title.is_some(), "Token_metadata.title is required");
 
-        token_metadata.description = series_metadata.description;
+        if self.enforce_metadata_hashes {
+            assert_metadata_hashes(&token_metadata);
+        }
+        self.metadata_limits.assert_within(&token_metadata);
+        if !self.allowed_media_types.is_empty() {
+            let media_type = media_type.as_ref().expect("Media type is required");
+            assert!(self.allowed_media_types.contains(media_type), "Media type not allowed");
+        }
 
-        token_metadata.reference = series_metadata.reference;
-        token_metadata.media = series_metadata.media;
-        token_metadata.copies = series_metadata.copies;
-        token_metadata.extra = series_metadata.extra;
+        // copies are capped by max_copies_per_series, open editions default to the cap
+        let mut token_metadata = token_metadata;
+        match (token_metadata.copies, self.max_copies_per_series) {
+            (None, max_copies) => token_metadata.copies = max_copies,
+            (Some(copies), Some(max_copies)) => assert!(copies <= max_copies, "Copies exceed max_copies_per_series"),
+            (Some(_), None) => {}
+        }
 
-        Some(Token {
-            token_id,
-            owner_id,
-            metadata: Some(token_metadata),
-            approved_account_ids,
-        })
-    }
-
-    // CUSTOM core standard repeated here because no macro below
-
-    pub fn nft_transfer_unsafe(
-        &mut self,
-        receiver_id: ValidAccountId,
-        token_id: TokenId,
-        approval_id: Option<u64>,
-        memo: Option<String>,
-    ) {
-        let sender_id = env::predecessor_account_id();
-        let receiver_id_str = receiver_id.to_string();
-        let (previous_owner_id, _) = self.tokens.internal_transfer(&sender_id, &receiver_id_str, &token_id, approval_id, memo.clone());
+        let royalty_res: HashMap<AccountId, u32> = royalty.unwrap_or_default();
+        assert_valid_royalty(&royalty_res);
+        if self.strict_royalty_accounts {
+            assert_named_royalty_accounts(&royalty_res);
+        }
+        if self.require_creator_royalty {
+            assert!(royalty_res.contains_key(&caller_id), "Royalty must include creator");
+        }
 
-        let authorized_id : Option<AccountId> = if sender_id != previous_owner_id {
-            Some(sender_id)
+        let price_res: Option<u128> = if price.is_some() {
+            assert!(
+                price.unwrap().0 < MAX_PRICE,
+                "Price is higher than {}",
+                MAX_PRICE
+            );
+            Some(price.unwrap().0)
         } else {
             None
         };
 
-        NearEvent::log_nft_transfer(
-            previous_owner_id,
-            receiver_id_str,
-            vec![token_id],
-            memo,
-            authorized_id,
+        self.token_series_by_id.insert(&token_series_id, &TokenSeries{
+            metadata: token_metadata.clone(),
+            creator_id: caller_id.to_string(),
+            tokens: UnorderedSet::new(
+                StorageKey::TokensBySeriesInner {
+                    token_series: token_series_id.clone(),
+                }
+                .try_to_vec()
+                .unwrap(),
+            ),
+            price: price_res,
+            is_mintable: true,
+            royalty: royalty_res.clone(),
+            auto_approve: true,
+            royalty_decay: Vec::new(),
+            primary_split: None,
+            charge_receiver_storage: false,
+            royalty_rounding: RoundingMode::Floor,
+            burnable_by_owner: true,
+            next_edition: 1,
+            revealed: true,
+            revealed_metadata: None,
+            non_mintable_at: None,
+            allow_custom_mint_metadata: false,
+            series_contract_metadata: None,
+            burn_on_transfer_reject: false,
+            max_sellable: None,
+            icon: None,
+            price_currency: None,
+            media_type,
+            token_lifetime_secs: None,
+        });
+
+        // set market data transaction fee
+        self.commit_fee_if_due();
+        let current_transaction_fee = self.calculate_current_transaction_fee();
+        self.market_data_transaction_fee.transaction_fee.insert(&token_series_id, &current_transaction_fee);
+
+        if let Some(idempotency_key) = idempotency_key {
+            self.series_id_by_idempotency_key.insert(&idempotency_key, &token_series_id);
+        }
+
+        // creator earns nothing on resales of tokens held by others
+        if !royalty_res.is_empty() && !royalty_res.contains_key(&caller_id) {
+            env::log(
+                json!({
+                    "type": "nft_create_series_warning",
+                    "params": {
+                        "token_series_id": token_series_id,
+                        "message": "creator_id is not a royalty recipient"
+                    }
+                })
+                .to_string()
+                .as_bytes(),
+            );
+        }
+
+        env::log(
+            json!({
+                "type": "nft_create_series",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "token_metadata": token_metadata,
+                    "creator_id": caller_id,
+                    "price": price,
+                    "royalty": royalty_res,
+                    "transaction_fee": &current_transaction_fee.to_string()
+                }
+            })
+            .to_string()
+            .as_bytes(),
         );
+        let mut creator_totals = self.creator_totals.get(&caller_id).unwrap_or_default();
+        creator_totals.series_count += 1;
+        self.creator_totals.insert(&caller_id, &creator_totals);
+
+        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
+
+		TokenSeriesJson{
+            token_series_id,
+			metadata: token_metadata,
+			creator_id: caller_id.into(),
+            royalty: royalty_res,
+            transaction_fee: Some(current_transaction_fee.into()),
+            burnable_by_owner: true,
+            is_mintable: true,
+		}
     }
 
     #[payable]
-    pub fn nft_transfer(
-        &mut self,
+    pub fn nft_buy(
+        &mut self, 
+        token_series_id: TokenSeriesId, 
         receiver_id: ValidAccountId,
-        token_id: TokenId,
-        approval_id: Option<u64>,
-        memo: Option<String>,
-    ) {
-        let sender_id = env::predecessor_account_id();
-        let previous_owner_id = self.tokens.owner_by_id.get(&token_id).expect("Token not found");
-        let receiver_id_str = receiver_id.to_string();
-        self.tokens.nft_transfer(receiver_id, token_id.clone(), approval_id, memo.clone());
+        refund_to: Option<ValidAccountId>,
+        token_metadata: Option<TokenMetadata>,
+    ) -> TokenId {
+        let initial_storage_usage = env::storage_usage();
 
-        let authorized_id : Option<AccountId> = if sender_id != previous_owner_id {
-            Some(sender_id)
-        } else {
-             None
-        };
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        let price: u128 = token_series.price.expect("Not for sale.");
+        // nft_buy only takes NEAR, the price of these series is in the token's units
+        assert!(token_series.price_currency.is_none(), "Series is priced in a fungible token");
+        if let Some(max_sellable) = token_series.max_sellable {
+            assert!(token_series.tokens.len() < max_sellable, "Series sale limit reached");
+        }
+        if let Some(allowlist) = self.allowlist_by_series.get(&token_series_id) {
+            assert!(allowlist.contains(receiver_id.as_ref()), "Receiver not allowlisted");
+        }
+        if let Some(mint_cooldown_secs) = self.mint_cooldown_secs {
+            let now = to_sec(env::block_timestamp());
+            if let Some(last_mint_time) = self.last_mint_time.get(receiver_id.as_ref()) {
+                assert!(now >= last_mint_time.saturating_add(mint_cooldown_secs), "Mint cooldown active");
+            }
+            self.last_mint_time.insert(receiver_id.as_ref(), &now);
+        }
+        if let Some(token_metadata) = token_metadata.as_ref() {
+            assert!(token_series.allow_custom_mint_metadata, "Custom mint metadata not allowed");
+            assert_custom_mint_metadata(token_metadata);
+            self.metadata_limits.assert_within(token_metadata);
+        }
+        let attached_deposit = env::attached_deposit();
+        assert!(
+            attached_deposit >= price,
+            "Attached deposit is less than price : {}",
+            price
+        );
+        // recorded before minting so a sold-out event includes this sale
+        self.commit_fee_if_due();
+        let volume = self.volume_by_series.get(&token_series_id).unwrap_or(0)
+            .checked_add(price)
+            .expect("Series volume overflow");
+        self.volume_by_series.insert(&token_series_id, &volume);
+        let sold = self.sold_by_series.get(&token_series_id).unwrap_or(0) + 1;
+        self.sold_by_series.insert(&token_series_id, &sold);
+        let mut creator_totals = self.creator_totals.get(&token_series.creator_id).unwrap_or_default();
+        creator_totals.total_volume = creator_totals.total_volume
+            .checked_add(price)
+            .expect("Creator volume overflow");
+        self.creator_totals.insert(&token_series.creator_id, &creator_totals);
+        let token_id: TokenId = self._nft_mint_series(token_series_id.clone(), receiver_id.to_string());
+        if let Some(custom_metadata) = token_metadata {
+            let token_metadata_by_id = self.tokens.token_metadata_by_id.as_mut().unwrap();
+            let mut metadata = token_metadata_by_id.get(&token_id).unwrap();
+            metadata.extra = custom_metadata.extra;
+            token_metadata_by_id.insert(&token_id, &metadata);
+        }
 
-        NearEvent::log_nft_transfer(
-            previous_owner_id,
-            receiver_id_str,
-            vec![token_id],
-            memo,
-             authorized_id,
+        let fee = self.calculate_market_data_transaction_fee(&token_series_id);
+        let mut for_treasury = price
+            .checked_mul(fee)
+            .expect("Treasury fee overflow")
+            / 10_000u128;
+        if fee != 0 && for_treasury < self.min_treasury_cut {
+            // tiny prices would otherwise floor the platform cut to nothing
+            for_treasury = std::cmp::min(self.min_treasury_cut, price);
+        }
+        let price_deducted = price.checked_sub(for_treasury).expect("Treasury fee exceeds price");
+        if let Some(primary_split) = token_series.primary_split {
+            for (account_id, amount) in split_primary_sale(&primary_split, price_deducted) {
+                if amount != 0 {
+                    self._pay_primary_share(account_id, amount);
+                }
+            }
+        } else if price_deducted != 0 {
+            // free mints (price 0) create no payment transfers
+            self._pay_primary_share(token_series.creator_id, price_deducted);
+        }
+
+        if for_treasury != 0 {
+            Promise::new(self.primary_treasury_id.clone())
+                .transfer(for_treasury)
+                .then(ext_self::on_treasury_transfer_failed(
+                    U128(for_treasury),
+                    &env::current_account_id(),
+                    NO_DEPOSIT,
+                    GAS_FOR_TREASURY_CALLBACK,
+                ));
+        }
+
+        let storage_used = env::storage_usage() - initial_storage_usage;
+        self.last_buy_storage_usage = storage_used;
+        // refund_deposit_to would only name the storage part, wallets need the total
+        let storage_cost = env::storage_byte_cost()
+            .checked_mul(Balance::from(storage_used))
+            .expect("Storage cost overflow");
+        let required = price.checked_add(storage_cost).expect("Buy cost overflow");
+        assert!(
+            attached_deposit >= required,
+            "Must attach {} yoctoNEAR to buy (price {} + storage {})",
+            required,
+            price,
+            storage_cost
+        );
+        refund_deposit_to(
+            storage_used,
+            price,
+            refund_to.map(|account_id| account_id.to_string()).unwrap_or_else(env::predecessor_account_id)
+        );
+
+        NearEvent::log_nft_mint(
+            receiver_id.to_string(),
+            vec![token_id.clone()],
+            Some(json!({"price": price.to_string()}).to_string())
         );
+
+        token_id
     }
 
+    /// New series with the metadata, royalty and price of source_series_id, no tokens are copied.
+    /// A price in a fungible token is not copied, the clone is priced in NEAR with overrides.price.
     #[payable]
-    pub fn nft_transfer_call(
+    pub fn nft_clone_series(
         &mut self,
+        source_series_id: TokenSeriesId,
+        overrides: Option<CloneOverrides>,
+    ) -> TokenSeriesJson {
+        let source = self.token_series_by_id.get(&source_series_id).expect("Token series not exist");
+        assert_eq!(env::predecessor_account_id(), source.creator_id, "Creator only");
+
+        let overrides = overrides.unwrap_or_default();
+        let price = overrides.price.or_else(|| source.price.filter(|_| source.price_currency.is_none()).map(U128));
+        let mut token_metadata = source.metadata;
+        if overrides.title.is_some() {
+            token_metadata.title = overrides.title;
+        }
+        if overrides.description.is_some() {
+            token_metadata.description = overrides.description;
+        }
+        if overrides.copies.is_some() {
+            token_metadata.copies = overrides.copies;
+        }
+
+        self.nft_create_series(None, token_metadata, price, Some(source.royalty), None, source.media_type)
+    }
+
+    #[payable]
+    pub fn nft_mint(
+        &mut self, 
+        token_series_id: TokenSeriesId, 
         receiver_id: ValidAccountId,
-        token_id: TokenId,
-        approval_id: Option<u64>,
-        memo: Option<String>,
-        msg: String,
-    ) -> PromiseOrValue<bool> {
-        assert_one_yocto();
-        let sender_id = env::predecessor_account_id();
-        let (previous_owner_id, old_approvals) = self.tokens.internal_transfer(
-            &sender_id,
-            receiver_id.as_ref(),
-            &token_id,
-            approval_id,
-            memo.clone(),
+        refund_to: Option<ValidAccountId>,
+    ) -> TokenId {
+        let initial_storage_usage = env::storage_usage();
+
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist.");
+        assert_eq!(env::predecessor_account_id(), token_series.creator_id, "Not a creator.");
+        let token_id: TokenId = self._nft_creator_mint(token_series_id, None, receiver_id.to_string());
+
+        let mut storage_used = env::storage_usage() - initial_storage_usage;
+        if token_series.charge_receiver_storage {
+            let storage_cost = env::storage_byte_cost() * Balance::from(storage_used);
+            let receiver_balance = self.storage_deposits.get(receiver_id.as_ref()).unwrap_or(0);
+            // fall back to the creator's attached deposit when the receiver can't cover it
+            if receiver_balance >= storage_cost {
+                self.storage_deposits.insert(receiver_id.as_ref(), &(receiver_balance - storage_cost));
+                storage_used = 0;
+            }
+        }
+
+        refund_deposit_to(
+            storage_used,
+            0,
+            refund_to.map(|account_id| account_id.to_string()).unwrap_or_else(env::predecessor_account_id)
         );
 
-        let authorized_id : Option<AccountId> = if sender_id != previous_owner_id {
-            Some(sender_id.clone())
-        } else {
-            None
-        };
+        NearEvent::log_nft_mint(
+            receiver_id.to_string(),
+            vec![token_id.clone()],
+            None,
+        );
 
-        NearEvent::log_nft_transfer(
-            previous_owner_id.clone(),
+        token_id
+    }
+
+    /// Prepays storage for tokens minted to `account_id` by creators
+    #[payable]
+    pub fn storage_deposit(&mut self, account_id: Option<ValidAccountId>) -> StorageBalance {
+        let initial_storage_usage = env::storage_usage();
+        let account_id: AccountId = account_id.map(|a| a.into()).unwrap_or_else(env::predecessor_account_id);
+
+        let previous_balance = self.storage_deposits.get(&account_id).unwrap_or(0);
+        let balance = previous_balance + env::attached_deposit();
+        self.storage_deposits.insert(&account_id, &balance);
+
+        // the balance entry itself is paid from the deposit
+        let entry_cost = env::storage_byte_cost() * Balance::from(env::storage_usage() - initial_storage_usage);
+        let balance = balance.checked_sub(entry_cost).expect("Deposit does not cover storage balance entry");
+        self.storage_deposits.insert(&account_id, &balance);
+        log_storage_deposit(&account_id, balance - previous_balance, balance);
+
+        StorageBalance { total: balance.into(), available: balance.into() }
+    }
+
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let balance = self.storage_deposits.get(&account_id).expect("No storage balance");
+        let amount: Balance = amount.map(|a| a.0).unwrap_or(balance);
+        assert!(amount <= balance, "Amount exceeds storage balance");
+
+        let balance = balance - amount;
+        self.storage_deposits.insert(&account_id, &balance);
+        log_storage_withdraw(&account_id, amount, balance);
+        if amount > 0 {
+            Promise::new(account_id).transfer(amount);
+        }
+
+        StorageBalance { total: balance.into(), available: balance.into() }
+    }
+
+    pub fn storage_balance_of(&self, account_id: ValidAccountId) -> Option<StorageBalance> {
+        self.storage_deposits.get(account_id.as_ref()).map(|balance| StorageBalance {
+            total: balance.into(),
+            available: balance.into(),
+        })
+    }
+
+    /// Mints a specific edition number, e.g. editions promised to collaborators
+    #[payable]
+    pub fn nft_mint_edition(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        edition: u64,
+        receiver_id: ValidAccountId,
+    ) -> TokenId {
+        let initial_storage_usage = env::storage_usage();
+
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(env::predecessor_account_id(), token_series.creator_id, "Not a creator");
+        let token_id: TokenId = self._nft_creator_mint(token_series_id, Some(edition), receiver_id.to_string());
+
+        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
+
+        NearEvent::log_nft_mint(
             receiver_id.to_string(),
             vec![token_id.clone()],
-            memo,
-            authorized_id,
+            None,
         );
 
-        // Initiating receiver's call and the callback
-        ext_non_fungible_token_receiver::nft_on_transfer(
-            sender_id,
-            previous_owner_id.clone(),
-            token_id.clone(),
-            msg,
-            receiver_id.as_ref(),
-            NO_DEPOSIT,
-            env::prepaid_gas() - GAS_FOR_NFT_TRANSFER_CALL,
-        )
-        .then(ext_self::nft_resolve_transfer(
-            previous_owner_id,
-            receiver_id.into(),
-            token_id,
-            old_approvals,
-            &env::current_account_id(),
-            NO_DEPOSIT,
-            GAS_FOR_RESOLVE_TRANSFER,
-        ))
-        .into()
+        token_id
+    }
+
+    /// Mints one token per receiver, stopping early once the series sells out.
+    /// Returns the number of tokens minted.
+    #[payable]
+    pub fn nft_batch_mint(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        receiver_ids: Vec<ValidAccountId>,
+    ) -> u64 {
+        let initial_storage_usage = env::storage_usage();
+
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(env::predecessor_account_id(), token_series.creator_id, "Not a creator");
+        assert!(token_series.mintable(), "Token series is not mintable");
+        assert!(
+            receiver_ids.len() <= MAX_BATCH_MINT,
+            "Cannot mint more than {} tokens in one batch",
+            MAX_BATCH_MINT
+        );
+
+        let minted = self._nft_batch_mint(&token_series_id, &receiver_ids);
+
+        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
+
+        minted
     }
 
-    // CUSTOM enumeration standard modified here because no macro below
+    /// Mints receiver_ids[start..start + count], a client drives a long list over several transactions.
+    /// Receivers before the stored cursor of (token_series_id, receiver_ids) are skipped so retries don't mint twice.
+    #[payable]
+    pub fn nft_batch_mint_range(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        receiver_ids: Vec<ValidAccountId>,
+        start: u64,
+        count: u64,
+    ) -> u64 {
+        let initial_storage_usage = env::storage_usage();
+
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(env::predecessor_account_id(), token_series.creator_id, "Not a creator");
+        assert!(token_series.mintable(), "Token series is not mintable");
+        assert!(
+            count as usize <= MAX_BATCH_MINT,
+            "Cannot mint more than {} tokens in one batch",
+            MAX_BATCH_MINT
+        );
+        let end = start.checked_add(count).expect("Range overflow");
+        assert!(end <= receiver_ids.len() as u64, "Range exceeds receiver_ids");
+
+        let cursor_key = batch_mint_cursor_key(&token_series_id, &receiver_ids);
+        let cursor = self.batch_mint_cursors.get(&cursor_key).unwrap_or(0);
+        assert!(start <= cursor, "Range must start at or before cursor {}", cursor);
+        let from = cursor.min(end);
+
+        let minted = self._nft_batch_mint(&token_series_id, &receiver_ids[from as usize..end as usize]);
+        if from + minted > cursor {
+            self.batch_mint_cursors.insert(&cursor_key, &(from + minted));
+        }
+
+        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
+
+        minted
+    }
+
+    /// Index of the first receiver nft_batch_mint_range hasn't minted yet
+    pub fn nft_batch_mint_cursor(&self, token_series_id: TokenSeriesId, receiver_ids: Vec<ValidAccountId>) -> u64 {
+        self.batch_mint_cursors
+            .get(&batch_mint_cursor_key(&token_series_id, &receiver_ids))
+            .unwrap_or(0)
+    }
+
+    fn _nft_batch_mint(&mut self, token_series_id: &TokenSeriesId, receiver_ids: &[ValidAccountId]) -> u64 {
+        let mut mint_data: Vec<NftMintData> = Vec::new();
+        for receiver_id in receiver_ids {
+            // the cap is crossed exactly once, on the last token minted here
+            if !self.token_series_by_id.get(token_series_id).unwrap().mintable() {
+                break;
+            }
+            let token_id: TokenId = self._nft_creator_mint(token_series_id.clone(), None, receiver_id.to_string());
+            mint_data.push(NftMintData {
+                owner_id: receiver_id.to_string(),
+                token_ids: vec![token_id],
+                memo: None,
+            });
+        }
+
+        let minted = mint_data.len() as u64;
+        if minted > 0 {
+            NearEvent::log_nft_mints(mint_data);
+        }
+        minted
+    }
+
+    /// Mints to receiver_id (the creator when None) and approves account_id on the new token
+    #[payable]
+    pub fn nft_mint_and_approve(
+        &mut self, 
+        token_series_id: TokenSeriesId, 
+        account_id: ValidAccountId,
+        msg: Option<String>,
+        receiver_id: Option<ValidAccountId>,
+    ) -> Option<Promise> {
+        let initial_storage_usage = env::storage_usage();
+
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(env::predecessor_account_id(), token_series.creator_id, "Not a creator");
+        let receiver_id: AccountId = receiver_id
+            .map(|receiver_id| receiver_id.into())
+            .unwrap_or(token_series.creator_id);
+        let account_id: AccountId = account_id.into();
+        assert_ne!(receiver_id, account_id, "Cannot approve the receiver");
+        let token_id: TokenId = self._nft_creator_mint(token_series_id, None, receiver_id.clone());
+
+        let approval_id: u64 = self._nft_add_approval(&token_id, &account_id);
+
+        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
+
+        NearEvent::log_nft_mint(
+            receiver_id.clone(),
+            vec![token_id.clone()],
+            None,
+        );
+
+        msg.map(|msg| {
+            ext_approval_receiver::nft_on_approve(
+                token_id,
+                receiver_id,
+                approval_id,
+                msg,
+                &account_id,
+                NO_DEPOSIT,
+                env::prepaid_gas() - GAS_FOR_NFT_APPROVE - GAS_FOR_MINT,
+            )
+        })
+    }
+
+    fn _nft_mint_series(
+        &mut self, 
+        token_series_id: TokenSeriesId, 
+        receiver_id: AccountId
+    ) -> TokenId {
+        self._nft_mint_edition(token_series_id, None, receiver_id)
+    }
+
+    /// Mint by the series creator, recorded so nft_revert_mint can undo it
+    fn _nft_creator_mint(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        edition: Option<u64>,
+        receiver_id: AccountId
+    ) -> TokenId {
+        let new_holder = !self.holders.contains(&receiver_id);
+        let token_id = self._nft_mint_edition(token_series_id, edition, receiver_id);
+        self.creator_minted.insert(&token_id, &new_holder);
+        token_id
+    }
+
+    /// Mints the given edition, or the lowest edition not yet minted when None
+    fn _nft_mint_edition(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        edition: Option<u64>,
+        receiver_id: AccountId
+    ) -> TokenId {
+        assert!(!self.retired, "Contract retired");
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series does not exist");
+        assert!(
+            token_series.mintable(),
+            "Token series is not mintable"
+        );
+
+        let num_tokens = token_series.tokens.len();
+        let max_copies = token_series.metadata.copies.unwrap_or(u64::MAX);
+        assert!(num_tokens < max_copies, "Series supply maxed");
+
+        if (num_tokens + 1) >= max_copies {
+            token_series.is_mintable = false;
+            env::log(
+                json!({
+                    "type": "nft_series_sold_out",
+                    "params": {
+                        "token_series_id": token_series_id,
+                        "final_supply": U64(max_copies),
+                        "total_volume": U128(self.volume_by_series.get(&token_series_id).unwrap_or(0)),
+                    }
+                })
+                .to_string()
+                .as_bytes(),
+            );
+        }
+
+        let edition = match edition {
+            Some(edition) => {
+                assert!(edition >= 1 && edition <= max_copies, "Edition out of range");
+                edition
+            }
+            None => {
+                let edition = token_series.first_free_edition(&token_series_id);
+                token_series.next_edition = edition + 1;
+                edition
+            }
+        };
+
+        let token_id = format!("{}{}{}", &token_series_id, TOKEN_DELIMETER, edition);
+        assert!(token_series.tokens.insert(&token_id), "Edition already minted");
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+
+        // you can add custom metadata to each token here
+        let metadata = Some(new_token_metadata(token_series.token_lifetime_secs));
+
+        //let token = self.tokens.mint(token_id, receiver_id, metadata);
+        // From : https://github.com/near/near-sdk-rs/blob/master/near-contract-standards/src/non_fungible_token/core/core_impl.rs#L359
+        // This allows lazy minting
+
+        let owner_id: AccountId = receiver_id;
+        self._track_holder(&owner_id);
+        self.tokens.owner_by_id.insert(&token_id, &owner_id);
+        self.total_minted += 1;
+        let mut creator_totals = self.creator_totals.get(&token_series.creator_id).unwrap_or_default();
+        creator_totals.total_minted += 1;
+        self.creator_totals.insert(&token_series.creator_id, &creator_totals);
+        self._record_ownership_change(&token_id, None, Some(owner_id.clone()));
+
+        let minted_key = format!("{}{}{}", &token_series_id, TOKEN_DELIMETER, &owner_id);
+        let minted_count = self.minted_per_owner.get(&minted_key).unwrap_or(0);
+        self.minted_per_owner.insert(&minted_key, &(minted_count + 1));
+
+        self.tokens
+            .token_metadata_by_id
+            .as_mut()
+            .and_then(|by_id| by_id.insert(&token_id, &metadata.as_ref().unwrap()));
+
+         if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
+             let mut token_ids = tokens_per_owner.get(&owner_id).unwrap_or_else(|| {
+                 UnorderedSet::new(StorageKey::TokensPerOwner {
+                     account_hash: env::sha256(&owner_id.as_bytes()),
+                 })
+             });
+             token_ids.insert(&token_id);
+             tokens_per_owner.insert(&owner_id, &token_ids);
+         }
+
+        if token_series.auto_approve {
+            if let Some(market_id) = self.auto_approve_market.clone() {
+                self._nft_add_approval(&token_id, &market_id);
+            }
+        }
+
+        token_id
+    }
+
+    /// Approval id the next nft_approve on the token will assign, 1 if it was never approved
+    pub fn nft_next_approval_id(&self, token_id: TokenId) -> U64 {
+        assert!(self.tokens.owner_by_id.get(&token_id).is_some(), "Token not found");
+        self.tokens
+            .next_approval_id_by_id
+            .as_ref()
+            .and_then(|next_approval_id_by_id| next_approval_id_by_id.get(&token_id))
+            .unwrap_or(1)
+            .into()
+    }
+
+    /// Approves account_id on every token owned by the caller, one nft_on_approve per token if msg is given
+    #[payable]
+    pub fn nft_batch_approve(
+        &mut self,
+        token_ids: Vec<TokenId>,
+        account_id: ValidAccountId,
+        msg: Option<String>,
+    ) -> Vec<U64> {
+        assert!(env::attached_deposit() >= 1, "Requires attached deposit of at least 1 yoctoNEAR");
+        assert!(!token_ids.is_empty(), "Token ids cannot be empty");
+        assert!(
+            token_ids.len() <= MAX_BATCH_APPROVE,
+            "Cannot approve more than {} tokens in one batch",
+            MAX_BATCH_APPROVE
+        );
+        let initial_storage_usage = env::storage_usage();
+
+        let owner_id = env::predecessor_account_id();
+        let account_id: AccountId = account_id.into();
+        let mut approval_ids: Vec<u64> = Vec::with_capacity(token_ids.len());
+        for token_id in token_ids.iter() {
+            let token_owner_id = self.tokens.owner_by_id.get(token_id).expect("Token not found");
+            assert_eq!(owner_id, token_owner_id, "Predecessor must be token owner.");
+            self._assert_approvals_within_limit(token_id, &account_id);
+            approval_ids.push(self._nft_add_approval(token_id, &account_id));
+        }
+
+        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
+
+        if let Some(msg) = msg {
+            // remaining gas is split evenly between the receiver calls, each keeping GAS_FOR_NFT_APPROVE for its receipt
+            let gas_per_call = ((env::prepaid_gas() - env::used_gas()) / token_ids.len() as Gas)
+                .checked_sub(GAS_FOR_NFT_APPROVE)
+                .expect("Not enough gas");
+            for (token_id, approval_id) in token_ids.into_iter().zip(approval_ids.iter()) {
+                ext_approval_receiver::nft_on_approve(
+                    token_id,
+                    owner_id.clone(),
+                    *approval_id,
+                    msg.clone(),
+                    &account_id,
+                    NO_DEPOSIT,
+                    gas_per_call,
+                );
+            }
+        }
+
+        approval_ids.into_iter().map(U64).collect()
+    }
+
+    fn _assert_approvals_within_limit(&self, token_id: &TokenId, account_id: &AccountId) {
+        let approved_account_ids = self.tokens.approvals_by_id
+            .as_ref()
+            .and_then(|approvals_by_id| approvals_by_id.get(token_id))
+            .unwrap_or_default();
+        assert!(
+            approved_account_ids.contains_key(account_id)
+                || (approved_account_ids.len() as u32) < self.max_approvals_per_token,
+            "Too many approvals"
+        );
+    }
+
+    fn _nft_add_approval(&mut self, token_id: &TokenId, account_id: &AccountId) -> u64 {
+        // Need to copy the nft_approve code here to solve the gas problem
+        // get contract-level LookupMap of token_id to approvals HashMap
+        let approvals_by_id = self.tokens.approvals_by_id.as_mut().unwrap();
+
+        // update HashMap of approvals for this token
+        let approved_account_ids =
+            &mut approvals_by_id.get(token_id).unwrap_or_else(|| HashMap::new());
+        let approval_id: u64 =
+            self.tokens.next_approval_id_by_id.as_ref().unwrap().get(token_id).unwrap_or_else(|| 1u64);
+        approved_account_ids.insert(account_id.clone(), approval_id);
+
+        // save updated approvals HashMap to contract's LookupMap
+        approvals_by_id.insert(token_id, &approved_account_ids);
+
+        // increment next_approval_id for this token
+        self.tokens.next_approval_id_by_id.as_mut().unwrap().insert(token_id, &(approval_id + 1));
+
+        approval_id
+    }
+
+    /// Series stays mintable for the grace period, the creator can cancel before it ends
+    #[payable]
+    pub fn nft_request_non_mintable(&mut self, token_series_id: TokenSeriesId) -> TimestampSec {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+        assert!(token_series.mintable(), "Already non-mintable");
+        assert!(token_series.non_mintable_at.is_none(), "Non-mintable already requested");
+        assert_eq!(
+            token_series.metadata.copies,
+            None,
+            "Decrease supply if copies not null"
+        );
+
+        let non_mintable_at = to_sec(env::block_timestamp()) + self.non_mintable_grace_period;
+        token_series.non_mintable_at = Some(non_mintable_at);
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_request_non_mintable",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "non_mintable_at": non_mintable_at,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+
+        non_mintable_at
+    }
+
+    #[payable]
+    pub fn nft_cancel_non_mintable(&mut self, token_series_id: TokenSeriesId) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+        assert!(token_series.non_mintable_at.is_some(), "Non-mintable not requested");
+        assert!(token_series.mintable(), "Grace period is over");
+
+        token_series.non_mintable_at = None;
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_cancel_non_mintable",
+                "params": {
+                    "token_series_id": token_series_id,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    #[payable]
+    pub fn nft_set_series_non_mintable(&mut self, token_series_id: TokenSeriesId) {
+        assert_one_yocto();
+
+        self._set_series_non_mintable(&token_series_id);
+        env::log(
+            json!({
+                "type": "nft_set_series_non_mintable",
+                "params": {
+                    "token_series_id": token_series_id,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    /// Closes several series at once, panics (reverting the whole batch) if any can't be closed
+    #[payable]
+    pub fn nft_batch_set_non_mintable(&mut self, token_series_ids: Vec<TokenSeriesId>) {
+        assert_one_yocto();
+        assert!(!token_series_ids.is_empty(), "Token series ids cannot be empty");
+        assert!(
+            token_series_ids.len() <= MAX_BATCH_SET_NON_MINTABLE,
+            "Cannot update more than {} series in one batch",
+            MAX_BATCH_SET_NON_MINTABLE
+        );
+
+        for token_series_id in &token_series_ids {
+            self._set_series_non_mintable(token_series_id);
+        }
+        env::log(
+            json!({
+                "type": "nft_batch_set_non_mintable",
+                "params": {
+                    "token_series_ids": token_series_ids,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    fn _set_series_non_mintable(&mut self, token_series_id: &TokenSeriesId) {
+        let mut token_series = self.token_series_by_id.get(token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        assert_eq!(
+            token_series.is_mintable,
+            true,
+            "Already non-mintable"
+        );
+
+        assert_eq!(
+            token_series.metadata.copies,
+            None,
+            "Decrease supply if copies not null"
+        );
+
+        token_series.is_mintable = false;
+        token_series.non_mintable_at = None;
+        self.token_series_by_id.insert(token_series_id, &token_series);
+    }
+
+    #[payable]
+    pub fn nft_decrease_series_copies(
+        &mut self, 
+        token_series_id: TokenSeriesId, 
+        decrease_copies: U64
+    ) -> U64 {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        let minted_copies = token_series.tokens.len();
+        let copies = token_series.metadata.copies.unwrap();
+
+        assert!(
+            (copies - decrease_copies.0) >= minted_copies,
+            "Cannot decrease supply, already minted: {}", minted_copies
+        );
+
+        let is_non_mintable = if (copies - decrease_copies.0) == minted_copies {
+            token_series.is_mintable = false;
+            true
+        } else {
+            false
+        };
+
+        token_series.metadata.copies = Some(copies - decrease_copies.0);
+
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_decrease_series_copies",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "copies": U64::from(token_series.metadata.copies.unwrap()),
+                    "is_non_mintable": is_non_mintable,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+        U64::from(token_series.metadata.copies.unwrap())
+    }
+
+    /// Marks the series metadata as a placeholder until nft_reveal_series, only before any mint
+    #[payable]
+    pub fn nft_set_series_unrevealed(&mut self, token_series_id: TokenSeriesId) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+        assert!(token_series.revealed, "Series already unrevealed");
+        assert!(token_series.tokens.is_empty(), "Series already minted");
+
+        token_series.revealed = false;
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+    }
+
+    /// Metadata shown once the series is revealed
+    #[payable]
+    pub fn nft_set_revealed_metadata(&mut self, token_series_id: TokenSeriesId, token_metadata: TokenMetadata) {
+        assert!(env::attached_deposit() >= 1, "Requires attached deposit of at least 1 yoctoNEAR");
+        let initial_storage_usage = env::storage_usage();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+        assert!(!token_series.revealed, "Series already revealed");
+
+        assert!(token_metadata.title.is_some(), "Token_metadata.title is required");
+        if self.enforce_metadata_hashes {
+            assert_metadata_hashes(&token_metadata);
+        }
+        self.metadata_limits.assert_within(&token_metadata);
+
+        token_series.revealed_metadata = Some(token_metadata);
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage), 0);
+    }
+
+    #[payable]
+    pub fn nft_reveal_series(&mut self, token_series_id: TokenSeriesId) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+        assert!(!token_series.revealed, "Series already revealed");
+
+        let mut token_metadata = token_series.revealed_metadata.take().expect("Revealed metadata not set");
+        // supply is managed by the series, not by the reveal
+        token_metadata.copies = token_series.metadata.copies;
+        token_series.metadata = token_metadata;
+        token_series.revealed = true;
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+
+        env::log(
+            json!({
+                "type": "nft_reveal_series",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "token_metadata": token_series.metadata,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    /// Closes the series for both capped and uncapped editions.
+    /// Capped series get their copies lowered to the minted supply.
+    #[payable]
+    pub fn nft_close_series(&mut self, token_series_id: TokenSeriesId) -> Option<U64> {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        assert!(token_series.is_mintable, "Already non-mintable");
+
+        if token_series.metadata.copies.is_some() {
+            token_series.metadata.copies = Some(token_series.tokens.len());
+        }
+        token_series.is_mintable = false;
+
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        let copies = token_series.metadata.copies.map(U64::from);
+        env::log(
+            json!({
+                "type": "nft_close_series",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "copies": copies,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+        copies
+    }
+
+    #[payable]
+    pub fn nft_set_series_auto_approve(&mut self, token_series_id: TokenSeriesId, auto_approve: bool) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        token_series.auto_approve = auto_approve;
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_auto_approve",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "auto_approve": auto_approve,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    #[payable]
+    pub fn nft_set_series_charge_receiver_storage(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        charge_receiver_storage: bool
+    ) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        token_series.charge_receiver_storage = charge_receiver_storage;
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_charge_receiver_storage",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "charge_receiver_storage": charge_receiver_storage,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    #[payable]
+    pub fn nft_set_series_royalty_rounding(&mut self, token_series_id: TokenSeriesId, royalty_rounding: RoundingMode) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        token_series.royalty_rounding = royalty_rounding;
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_royalty_rounding",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "royalty_rounding": royalty_rounding,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    #[payable]
+    pub fn nft_set_series_burnable_by_owner(&mut self, token_series_id: TokenSeriesId, burnable_by_owner: bool) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        token_series.burnable_by_owner = burnable_by_owner;
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_burnable_by_owner",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "burnable_by_owner": burnable_by_owner,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    #[payable]
+    pub fn nft_set_series_allow_custom_mint_metadata(&mut self, token_series_id: TokenSeriesId, allow_custom_mint_metadata: bool) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        token_series.allow_custom_mint_metadata = allow_custom_mint_metadata;
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_allow_custom_mint_metadata",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "allow_custom_mint_metadata": allow_custom_mint_metadata,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    #[payable]
+    pub fn nft_set_series_contract_metadata(&mut self, token_series_id: TokenSeriesId, series_contract_metadata: Option<SeriesMeta>) {
+        assert!(env::attached_deposit() >= 1, "Requires attached deposit of at least 1 yoctoNEAR");
+        let initial_storage_usage = env::storage_usage();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        token_series.series_contract_metadata = series_contract_metadata.clone();
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_contract_metadata",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "series_contract_metadata": series_contract_metadata,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage), 0);
+    }
+
+    /// Replaces the series allowlist, the creator pays its storage
+    #[payable]
+    pub fn nft_set_series_allowlist(&mut self, token_series_id: TokenSeriesId, accounts: Vec<ValidAccountId>) {
+        assert!(env::attached_deposit() >= 1, "Requires attached deposit of at least 1 yoctoNEAR");
+        let initial_storage_usage = env::storage_usage();
+
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+        assert!(!accounts.is_empty(), "Allowlist cannot be empty, clear it instead");
+
+        let mut allowlist = self.allowlist_by_series.get(&token_series_id).unwrap_or_else(|| {
+            UnorderedSet::new(
+                StorageKey::AllowlistBySeriesInner {
+                    token_series: token_series_id.clone(),
+                }
+                .try_to_vec()
+                .unwrap(),
+            )
+        });
+        allowlist.clear();
+        for account_id in &accounts {
+            allowlist.insert(account_id.as_ref());
+        }
+        self.allowlist_by_series.insert(&token_series_id, &allowlist);
+        env::log(
+            json!({
+                "type": "nft_set_series_allowlist",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "accounts": accounts,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage), 0);
+    }
+
+    /// Removes the series allowlist, opening nft_buy to everyone
+    #[payable]
+    pub fn nft_clear_series_allowlist(&mut self, token_series_id: TokenSeriesId) {
+        assert_one_yocto();
+
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        let mut allowlist = self.allowlist_by_series.remove(&token_series_id).expect("Series has no allowlist");
+        allowlist.clear();
+        env::log(
+            json!({
+                "type": "nft_clear_series_allowlist",
+                "params": {
+                    "token_series_id": token_series_id,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    #[payable]
+    pub fn nft_set_series_icon(&mut self, token_series_id: TokenSeriesId, icon: Option<String>) {
+        assert!(env::attached_deposit() >= 1, "Requires attached deposit of at least 1 yoctoNEAR");
+        let initial_storage_usage = env::storage_usage();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+        if let Some(icon) = &icon {
+            assert!(
+                icon.len() <= MAX_SERIES_ICON_LEN,
+                "Icon exceeds {} bytes",
+                MAX_SERIES_ICON_LEN
+            );
+        }
+
+        token_series.icon = icon.clone();
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_icon",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "icon": icon,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage), 0);
+    }
+
+    #[payable]
+    pub fn nft_set_series_price_currency(&mut self, token_series_id: TokenSeriesId, price_currency: Option<CurrencyInfo>) {
+        assert!(env::attached_deposit() >= 1, "Requires attached deposit of at least 1 yoctoNEAR");
+        let initial_storage_usage = env::storage_usage();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+        if let Some(price_currency) = &price_currency {
+            assert!(is_valid_account_id(price_currency.contract.as_bytes()), "Not valid account_id for currency contract");
+            assert!(!price_currency.symbol.is_empty(), "Currency symbol is required");
+        }
+
+        token_series.price_currency = price_currency.clone();
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_price_currency",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "price_currency": price_currency,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage), 0);
+    }
+
+    /// Lifetime of tokens minted from now on, already minted tokens keep their expires_at
+    #[payable]
+    pub fn nft_set_series_token_lifetime(&mut self, token_series_id: TokenSeriesId, token_lifetime_secs: Option<u32>) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        token_series.token_lifetime_secs = token_lifetime_secs;
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_token_lifetime",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "token_lifetime_secs": token_lifetime_secs,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    pub fn nft_is_expired(&self, token_id: TokenId) -> bool {
+        assert!(self.tokens.owner_by_id.get(&token_id).is_some(), "Token not found");
+        self._is_expired(&token_id)
+    }
+
+    /// expires_at is a block timestamp in nanoseconds, like issued_at
+    fn _is_expired(&self, token_id: &TokenId) -> bool {
+        self.tokens
+            .token_metadata_by_id
+            .as_ref()
+            .and_then(|by_id| by_id.get(token_id))
+            .and_then(|metadata| metadata.expires_at)
+            .and_then(|expires_at| expires_at.parse::<Timestamp>().ok())
+            .map(|expires_at| env::block_timestamp() >= expires_at)
+            .unwrap_or(false)
+    }
+
+    #[payable]
+    pub fn nft_set_series_burn_on_transfer_reject(&mut self, token_series_id: TokenSeriesId, burn_on_transfer_reject: bool) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        token_series.burn_on_transfer_reject = burn_on_transfer_reject;
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_burn_on_transfer_reject",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "burn_on_transfer_reject": burn_on_transfer_reject,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    #[payable]
+    pub fn nft_set_series_max_sellable(&mut self, token_series_id: TokenSeriesId, max_sellable: Option<u64>) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        token_series.max_sellable = max_sellable;
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_max_sellable",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "max_sellable": max_sellable.map(U64::from),
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    #[payable]
+    pub fn nft_set_series_royalty_decay(&mut self, token_series_id: TokenSeriesId, royalty_decay: Vec<u32>) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        assert!(royalty_decay.len() <= 10, "Royalty decay exceeds 10 steps");
+        for step in royalty_decay.iter() {
+            assert!(*step <= 10_000, "Royalty decay step is more than 10_000");
+        }
+
+        token_series.royalty_decay = royalty_decay.clone();
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_royalty_decay",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "royalty_decay": royalty_decay,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    /// Splits primary sale proceeds (after treasury fee) instead of paying the creator alone
+    #[payable]
+    pub fn nft_set_series_primary_split(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        primary_split: Option<Vec<(AccountId, u32)>>
+    ) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        if let Some(primary_split) = &primary_split {
+            assert!(!primary_split.is_empty(), "Primary split is empty");
+            assert!(primary_split.len() <= 10, "Primary split exceeds 10 accounts");
+            let mut total_bps: u32 = 0;
+            for (account_id, bps) in primary_split.iter() {
+                if !is_valid_account_id(account_id.as_bytes()) {
+                    env::panic("Not valid account_id for primary split".as_bytes());
+                }
+                total_bps = total_bps.checked_add(*bps).expect("Primary split overflow");
+            }
+            assert_eq!(total_bps, 10_000, "Primary split must sum to 10_000");
+        }
+
+        token_series.primary_split = primary_split.clone();
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_primary_split",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "primary_split": primary_split,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    /// Collaborator shares of primary sales, the creator keeps the residual.
+    /// Stored as the equivalent primary split with the creator first.
+    #[payable]
+    pub fn nft_set_series_primary_royalty(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        primary_royalty: Option<HashMap<AccountId, u32>>
+    ) {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        let primary_split = primary_royalty.as_ref().map(|primary_royalty| {
+            assert!(primary_royalty.len() <= 10, "Primary royalty exceeds 10 accounts");
+            let mut collaborators: Vec<(AccountId, u32)> = primary_royalty
+                .iter()
+                .filter(|(account_id, _)| **account_id != token_series.creator_id)
+                .map(|(account_id, bps)| {
+                    if !is_valid_account_id(account_id.as_bytes()) {
+                        env::panic("Not valid account_id for primary royalty".as_bytes());
+                    }
+                    (account_id.clone(), *bps)
+                })
+                .collect();
+            collaborators.sort();
+
+            let total_bps = collaborators
+                .iter()
+                .try_fold(0u32, |total, (_, bps)| total.checked_add(*bps))
+                .expect("Primary royalty overflow");
+            assert!(total_bps <= 10_000, "Primary royalty exceeds 10_000");
+
+            let mut primary_split = vec![(token_series.creator_id.clone(), 10_000 - total_bps)];
+            primary_split.extend(collaborators);
+            primary_split
+        });
+
+        token_series.primary_split = primary_split;
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        env::log(
+            json!({
+                "type": "nft_set_series_primary_royalty",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "primary_royalty": primary_royalty,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    #[payable]
+    pub fn nft_set_series_price(&mut self, token_series_id: TokenSeriesId, price: Option<U128>) -> Option<U128> {
+        assert_one_yocto();
+
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        assert!(token_series.mintable(), "Token series is not mintable");
+
+        if price.is_none() {
+            token_series.price = None;
+        } else {
+            assert!(
+                price.unwrap().0 < MAX_PRICE,
+                "Price is higher than {}",
+                MAX_PRICE
+            );
+            token_series.price = Some(price.unwrap().0);
+        }
+
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+
+        // set market data transaction fee
+        self.commit_fee_if_due();
+        let current_transaction_fee = self.calculate_current_transaction_fee();
+        self.market_data_transaction_fee.transaction_fee.insert(&token_series_id, &current_transaction_fee);
+
+        env::log(
+            json!({
+                "type": "nft_set_series_price",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "price": price,
+                    "transaction_fee": current_transaction_fee.to_string()
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+        return price;
+    }
+
+    #[payable]
+    pub fn nft_burn(&mut self, token_id: TokenId) {
+        assert_one_yocto();
+
+        let owner_id = self.tokens.owner_by_id.get(&token_id).unwrap();
+        assert_eq!(
+            owner_id,
+            env::predecessor_account_id(),
+            "Token owner only"
+        );
+
+        let token_series_id: TokenSeriesId = token_id.split(TOKEN_DELIMETER).next().unwrap().to_string();
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        // the creator may still burn editions it holds
+        assert!(
+            token_series.burnable_by_owner || owner_id == token_series.creator_id,
+            "Series not burnable"
+        );
+
+        self._nft_burn(&token_id, &owner_id);
+
+        NearEvent::log_nft_burn(
+            owner_id,
+            vec![token_id],
+            None,
+            None,
+        );
+    }
+
+    /// Royalty used for token_id instead of its series royalty, None restores the series royalty
+    #[payable]
+    pub fn nft_set_token_royalty(&mut self, token_id: TokenId, royalty: Option<HashMap<AccountId, u32>>) {
+        assert!(env::attached_deposit() >= 1, "Requires attached deposit of at least 1 yoctoNEAR");
+        let initial_storage_usage = env::storage_usage();
+
+        let token_series_id: TokenSeriesId = token_id.split(TOKEN_DELIMETER).next().unwrap().to_string();
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+        assert!(self.tokens.owner_by_id.get(&token_id).is_some(), "Token not found");
+
+        if let Some(royalty) = &royalty {
+            assert_valid_royalty(royalty);
+            if self.strict_royalty_accounts {
+                assert_named_royalty_accounts(royalty);
+            }
+            self.token_royalty_override.insert(&token_id, royalty);
+        } else {
+            self.token_royalty_override.remove(&token_id);
+        }
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage), 0);
+
+        env::log(
+            json!({
+                "type": "nft_set_token_royalty",
+                "params": {
+                    "token_id": token_id,
+                    "royalty": royalty,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    /// Moves the royalty bps of old to new on the caller's series within a page of all series.
+    /// Per-token royalty overrides are left untouched. Returns the updated series ids.
+    #[payable]
+    pub fn nft_replace_royalty_recipient(
+        &mut self,
+        old: AccountId,
+        new: ValidAccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<TokenSeriesId> {
+        assert!(env::attached_deposit() >= 1, "Requires attached deposit of at least 1 yoctoNEAR");
+        let initial_storage_usage = env::storage_usage();
+
+        let creator_id = env::predecessor_account_id();
+        let new: AccountId = new.into();
+        assert_ne!(old, new, "Royalty recipient unchanged");
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        assert!(
+            (self.token_series_by_id.len() as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        let matching: Vec<(TokenSeriesId, TokenSeries)> = self.token_series_by_id
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .filter(|(_, token_series)| {
+                token_series.creator_id == creator_id && token_series.royalty.contains_key(&old)
+            })
+            .collect();
+
+        let mut token_series_ids = vec![];
+        for (token_series_id, mut token_series) in matching {
+            let bps = token_series.royalty.remove(&old).unwrap();
+            *token_series.royalty.entry(new.clone()).or_insert(0) += bps;
+            if self.strict_royalty_accounts {
+                assert_named_royalty_accounts(&token_series.royalty);
+            }
+            self.token_series_by_id.insert(&token_series_id, &token_series);
+            token_series_ids.push(token_series_id);
+        }
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage), 0);
+
+        env::log(
+            json!({
+                "type": "nft_replace_royalty_recipient",
+                "params": {
+                    "creator_id": creator_id,
+                    "old": old,
+                    "new": new,
+                    "token_series_ids": token_series_ids,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+        token_series_ids
+    }
+
+    /// Burns an edition the series creator holds, ignoring burnable_by_owner
+    #[payable]
+    pub fn nft_creator_burn(&mut self, token_id: TokenId) {
+        assert_one_yocto();
+
+        let token_series_id: TokenSeriesId = token_id.split(TOKEN_DELIMETER).next().unwrap().to_string();
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        let creator_id = env::predecessor_account_id();
+        assert_eq!(creator_id, token_series.creator_id, "Creator only");
+
+        let owner_id = self.tokens.owner_by_id.get(&token_id).expect("Token not found");
+        assert_eq!(owner_id, creator_id, "Token owner only");
+
+        self._nft_burn(&token_id, &owner_id);
+
+        NearEvent::log_nft_burn(
+            owner_id,
+            vec![token_id],
+            None,
+            None,
+        );
+    }
+
+    /// Removes a token the creator minted to the wrong account, only within REVERT_MINT_WINDOW and before any transfer.
+    /// The edition can be minted again and the token no longer counts as minted.
+    #[payable]
+    pub fn nft_revert_mint(&mut self, token_id: TokenId) {
+        assert_one_yocto();
+
+        let token_series_id: TokenSeriesId = token_id.split(TOKEN_DELIMETER).next().unwrap().to_string();
+        let mut token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(env::predecessor_account_id(), token_series.creator_id, "Creator only");
+
+        let owner_id = self.tokens.owner_by_id.get(&token_id).expect("Token not found");
+        let new_holder = self.creator_minted.get(&token_id).expect("Only creator mints can be reverted");
+        assert!(self._is_in_revert_window(&token_id), "Revert window is over");
+        assert!(!self.transferred_in_revert_window.contains(&token_id), "Token already transferred");
+
+        self._nft_burn(&token_id, &owner_id);
+        self.total_burned -= 1;
+        let burned = self.burned_by_series.get(&token_series_id).unwrap_or(1) - 1;
+        self.burned_by_series.insert(&token_series_id, &burned);
+        self.total_minted -= 1;
+        let mut creator_totals = self.creator_totals.get(&token_series.creator_id).unwrap_or_default();
+        creator_totals.total_minted = creator_totals.total_minted.saturating_sub(1);
+        self.creator_totals.insert(&token_series.creator_id, &creator_totals);
+
+        let minted_key = format!("{}{}{}", &token_series_id, TOKEN_DELIMETER, &owner_id);
+        let minted_count = self.minted_per_owner.get(&minted_key).unwrap_or(1);
+        if minted_count > 1 {
+            self.minted_per_owner.insert(&minted_key, &(minted_count - 1));
+        } else {
+            self.minted_per_owner.remove(&minted_key);
+        }
+        let holds_tokens = self.tokens.tokens_per_owner
+            .as_ref()
+            .and_then(|tokens_per_owner| tokens_per_owner.get(&owner_id))
+            .map(|token_ids| !token_ids.is_empty())
+            .unwrap_or(false);
+        if new_holder && !holds_tokens {
+            self.holders.remove(&owner_id);
+            self.distinct_owners -= 1;
+        }
+
+        // the reverted token may have been the one that sold the series out
+        let was_sold_out = token_series.metadata.copies == Some(token_series.tokens.len());
+        token_series.tokens.remove(&token_id);
+        if was_sold_out {
+            token_series.is_mintable = true;
+        }
+        let edition: u64 = token_id.split(TOKEN_DELIMETER).nth(1).unwrap().parse().unwrap();
+        token_series.next_edition = token_series.next_edition.min(edition);
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+
+        NearEvent::log_nft_burn(
+            owner_id,
+            vec![token_id],
+            Some("revert_mint".to_string()),
+            Some(token_series.creator_id),
+        );
+    }
+
+    fn _is_in_revert_window(&self, token_id: &TokenId) -> bool {
+        self.tokens.token_metadata_by_id
+            .as_ref()
+            .and_then(|token_metadata_by_id| token_metadata_by_id.get(token_id))
+            .and_then(|metadata| metadata.issued_at)
+            .and_then(|issued_at| issued_at.parse::<Timestamp>().ok())
+            .map(|issued_at| env::block_timestamp().saturating_sub(issued_at) <= REVERT_MINT_WINDOW)
+            .unwrap_or(false)
+    }
+
+    /// Series whose tokens can be burned to mint from token_series_id
+    #[payable]
+    pub fn nft_set_series_upgrade_inputs(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        input_series_ids: Vec<TokenSeriesId>
+    ) {
+        assert_one_yocto();
+
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Creator only"
+        );
+
+        for input_series_id in input_series_ids.iter() {
+            let input_series = self.token_series_by_id.get(input_series_id).expect("Input token series not exist");
+            assert_eq!(
+                input_series.creator_id,
+                token_series.creator_id,
+                "Input series must have the same creator"
+            );
+        }
+
+        if input_series_ids.is_empty() {
+            self.upgrade_inputs_by_series.remove(&token_series_id);
+        } else {
+            self.upgrade_inputs_by_series.insert(&token_series_id, &input_series_ids);
+        }
+
+        env::log(
+            json!({
+                "type": "nft_set_series_upgrade_inputs",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "input_series_ids": input_series_ids,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    pub fn nft_get_series_upgrade_inputs(&self, token_series_id: TokenSeriesId) -> Vec<TokenSeriesId> {
+        self.upgrade_inputs_by_series.get(&token_series_id).unwrap_or_default()
+    }
+
+    #[payable]
+    pub fn nft_burn_and_mint(
+        &mut self,
+        burn_token_ids: Vec<TokenId>,
+        target_series_id: TokenSeriesId,
+        receiver_id: ValidAccountId
+    ) -> TokenId {
+        let initial_storage_usage = env::storage_usage();
+        let owner_id = env::predecessor_account_id();
+
+        assert!(!burn_token_ids.is_empty(), "Nothing to burn");
+        assert!(
+            burn_token_ids.len() <= MAX_BATCH_BURN,
+            "Cannot burn more than {} tokens in one batch",
+            MAX_BATCH_BURN
+        );
+        let input_series_ids = self.upgrade_inputs_by_series.get(&target_series_id).expect("Token series is not upgradable");
+
+        for token_id in burn_token_ids.iter() {
+            let token_owner_id = self.tokens.owner_by_id.get(token_id).expect("Token not found");
+            assert_eq!(token_owner_id, owner_id, "Token owner only");
+
+            let token_series_id: TokenSeriesId = token_id.split(TOKEN_DELIMETER).next().unwrap().to_string();
+            assert!(
+                input_series_ids.contains(&token_series_id),
+                "Token {} is not an upgrade input", token_id
+            );
+            let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+            assert!(
+                token_series.burnable_by_owner || owner_id == token_series.creator_id,
+                "Series not burnable"
+            );
+
+            self._nft_burn(token_id, &owner_id);
+        }
+
+        let token_id: TokenId = self._nft_mint_series(target_series_id, receiver_id.to_string());
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage), 0);
+
+        NearEvent::log_nft_burn(
+            owner_id,
+            burn_token_ids,
+            None,
+            None,
+        );
+
+        NearEvent::log_nft_mint(
+            receiver_id.to_string(),
+            vec![token_id.clone()],
+            None,
+        );
+
+        token_id
+    }
+
+    fn _nft_burn(&mut self, token_id: &TokenId, owner_id: &AccountId) {
+        self._assert_not_locked(token_id);
+
+        if let Some(next_approval_id_by_id) = &mut self.tokens.next_approval_id_by_id {
+            next_approval_id_by_id.remove(token_id);
+        }
+
+        if let Some(approvals_by_id) = &mut self.tokens.approvals_by_id {
+            approvals_by_id.remove(token_id);
+        }
+
+        if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
+            let mut token_ids = tokens_per_owner.get(owner_id).unwrap();
+            token_ids.remove(token_id);
+            tokens_per_owner.insert(owner_id, &token_ids);
+        }
+
+        if let Some(token_metadata_by_id) = &mut self.tokens.token_metadata_by_id {
+            token_metadata_by_id.remove(token_id);
+        }
+
+        self.tokens.owner_by_id.remove(token_id);
+        self.resale_count_by_token.remove(token_id);
+        self.token_royalty_override.remove(token_id);
+        self.transferred_in_revert_window.remove(token_id);
+        self.creator_minted.remove(token_id);
+        self._record_ownership_change(token_id, Some(owner_id.clone()), None);
+        self.total_burned += 1;
+        let token_series_id: TokenSeriesId = token_id.split(TOKEN_DELIMETER).next().unwrap().to_string();
+        let burned = self.burned_by_series.get(&token_series_id).unwrap_or(0) + 1;
+        self.burned_by_series.insert(&token_series_id, &burned);
+    }
+
+    // CUSTOM VIEWS
+
+	pub fn nft_get_series_single(&self, token_series_id: TokenSeriesId) -> TokenSeriesJson {
+		self.nft_try_get_series_single(token_series_id).expect("Series does not exist")
+	}
+
+    /// Same as nft_get_series_single, but None for a missing series instead of a panic
+    pub fn nft_try_get_series_single(&self, token_series_id: TokenSeriesId) -> Option<TokenSeriesJson> {
+        let token_series = self.token_series_by_id.get(&token_series_id)?;
+        let current_transaction_fee = self.get_market_data_transaction_fee(&token_series_id);
+        Some(TokenSeriesJson{
+            token_series_id,
+            is_mintable: token_series.mintable(),
+            metadata: token_series.metadata,
+            creator_id: token_series.creator_id,
+            royalty: token_series.royalty,
+            transaction_fee: Some(current_transaction_fee.into()),
+            burnable_by_owner: token_series.burnable_by_owner,
+        })
+    }
+
+    pub fn nft_get_series_format(self) -> (char, &'static str, &'static str) {
+        (TOKEN_DELIMETER, TITLE_DELIMETER, EDITION_DELIMETER)
+    }
+
+    pub fn nft_get_series_price(self, token_series_id: TokenSeriesId) -> Option<U128> {
+        let price = self.token_series_by_id.get(&token_series_id).unwrap().price;
+        match price {
+            Some(p) => return Some(U128::from(p)),
+            None => return None
+        };
+    }
+
+    /// Whether nft_buy can currently mint from the series, and why not
+    pub fn nft_series_sale_status(&self, token_series_id: TokenSeriesId) -> SaleStatus {
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        let reason = token_series.not_for_sale_reason();
+
+        SaleStatus {
+            price: token_series.price.map(U128::from),
+            available: reason.is_none(),
+            reason: reason.map(|reason| reason.to_string()),
+        }
+    }
+
+    /// nft_get_series ordered by numeric series id, stable across pages while series are added
+    pub fn nft_get_series_sorted(
+        &self,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+        only_for_sale: Option<bool>,
+    ) -> Vec<TokenSeriesJson> {
+        let token_series_ids = self.token_series_by_id.keys_as_vector();
+        assert!(
+            token_series_ids.len() <= MAX_SORTED_SERIES,
+            "More than {} series, use nft_get_series",
+            MAX_SORTED_SERIES
+        );
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        assert!(
+            (token_series_ids.len() as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        let mut token_series_ids = token_series_ids.to_vec();
+        token_series_ids.sort_by_key(|token_series_id| token_series_id.parse::<u64>().unwrap_or(u64::MAX));
+        token_series_ids
+            .into_iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|token_series_id| {
+                let token_series = self.token_series_by_id.get(&token_series_id).unwrap();
+                (token_series_id, token_series)
+            })
+            .filter(|(_, token_series)| {
+                !only_for_sale.unwrap_or(false) || token_series.not_for_sale_reason().is_none()
+            })
+            .map(|(token_series_id, token_series)| TokenSeriesJson{
+                token_series_id,
+                is_mintable: token_series.mintable(),
+                metadata: token_series.metadata,
+                creator_id: token_series.creator_id,
+                royalty: token_series.royalty,
+                transaction_fee: None,
+                burnable_by_owner: token_series.burnable_by_owner,
+            })
+            .collect()
+    }
+
+    /// With only_for_sale, `limit` bounds the number of series scanned,
+    /// so fewer than `limit` purchasable series may be returned.
+    pub fn nft_get_series(
+        &self,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+        only_for_sale: Option<bool>,
+    ) -> Vec<TokenSeriesJson> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        assert!(
+            (self.token_series_by_id.len() as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        self.token_series_by_id
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .filter(|(_, token_series)| {
+                !only_for_sale.unwrap_or(false) || token_series.not_for_sale_reason().is_none()
+            })
+            .map(|(token_series_id, token_series)| TokenSeriesJson{
+                token_series_id,
+                is_mintable: token_series.mintable(),
+                metadata: token_series.metadata,
+                creator_id: token_series.creator_id,
+                royalty: token_series.royalty,
+                transaction_fee: None,
+                burnable_by_owner: token_series.burnable_by_owner,
+            })
+            .collect()
+    }
+
+    /// Series whose extra JSON has key set to the string value, series without parseable extra are skipped.
+    /// Scans limit series from from_index, meant for discovery and not for hot paths.
+    pub fn nft_series_by_tag(
+        &self,
+        key: String,
+        value: String,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<TokenSeriesJson> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        assert!(
+            (self.token_series_by_id.len() as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        self.token_series_by_id
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .filter(|(_, token_series)| {
+                token_series.metadata.extra
+                    .as_ref()
+                    .and_then(|extra| near_sdk::serde_json::from_str::<near_sdk::serde_json::Value>(extra).ok())
+                    .map(|extra| extra.get(&key).and_then(|tag| tag.as_str()) == Some(value.as_str()))
+                    .unwrap_or(false)
+            })
+            .map(|(token_series_id, token_series)| TokenSeriesJson{
+                token_series_id,
+                is_mintable: token_series.mintable(),
+                metadata: token_series.metadata,
+                creator_id: token_series.creator_id,
+                royalty: token_series.royalty,
+                transaction_fee: None,
+                burnable_by_owner: token_series.burnable_by_owner,
+            })
+            .collect()
+    }
+
+    /// Series ordered by current supply, most minted first.
+    /// Only the first MAX_POPULARITY_SCAN series are ranked since the whole ranking is built in memory.
+    pub fn nft_series_by_popularity(
+        &self,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<TokenSeriesJson> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        let scanned = (self.token_series_by_id.len() as usize).min(MAX_POPULARITY_SCAN);
+        assert!(
+            (scanned as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        let mut supply_by_series: Vec<(TokenSeriesId, u64)> = self.token_series_by_id
+            .iter()
+            .take(scanned)
+            .map(|(token_series_id, token_series)| (token_series_id, token_series.tokens.len()))
+            .collect();
+        // stable sort, series with equal supply keep creation order
+        supply_by_series.sort_by_key(|(_, supply)| std::cmp::Reverse(*supply));
+
+        supply_by_series
+            .into_iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|(token_series_id, _)| self.nft_get_series_single(token_series_id))
+            .collect()
+    }
+
+    /// Royalty bps summed per recipient over a page of series, sorted by account_id.
+    /// from_index and limit page over series like nft_get_series, sum the pages for contract-wide totals.
+    pub fn nft_royalty_recipients(
+        &self,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<(AccountId, u32)> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        assert!(
+            (self.token_series_by_id.len() as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        let mut royalty_by_recipient: HashMap<AccountId, u32> = HashMap::new();
+        for token_series in self.token_series_by_id
+            .values_as_vector()
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+        {
+            for (account_id, bps) in token_series.royalty.iter() {
+                *royalty_by_recipient.entry(account_id.clone()).or_insert(0) += *bps;
+            }
+        }
+
+        let mut recipients: Vec<(AccountId, u32)> = royalty_by_recipient.into_iter().collect();
+        recipients.sort();
+        recipients
+    }
+
+    /// Every series where account_id is a royalty recipient. Scans every series.
+    pub fn nft_royalty_for_recipient(&self, account_id: ValidAccountId) -> Vec<(TokenSeriesId, u32)> {
+        self.token_series_by_id
+            .iter()
+            .filter_map(|(token_series_id, token_series)| {
+                let bps = token_series.royalty.get(account_id.as_ref())?;
+                Some((token_series_id, *bps))
+            })
+            .collect()
+    }
+
+    pub fn nft_series_contract_metadata(&self, token_series_id: TokenSeriesId) -> Option<SeriesMeta> {
+        self.token_series_by_id.get(&token_series_id).expect("Token series not exist").series_contract_metadata
+    }
+
+    /// Deposit to attach to nft_buy: the price plus the storage estimate of the last purchase
+    pub fn nft_quote_buy(&self, token_series_id: TokenSeriesId) -> U128 {
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        let price: u128 = token_series.price.expect("Not for sale.");
+        let storage_cost = env::storage_byte_cost() * Balance::from(self.last_buy_storage_usage);
+        U128(price + storage_cost)
+    }
+
+    /// Refund nft_buy would send for `attached`, storage is estimated from the latest nft_buy
+    pub fn nft_buy_change(&self, token_series_id: TokenSeriesId, attached: U128) -> U128 {
+        let change = attached.0.saturating_sub(self.nft_quote_buy(token_series_id).0);
+        // refund_deposit_to skips refunds of 1 yoctoNEAR or less
+        if change > 1 {
+            U128(change)
+        } else {
+            U128(0)
+        }
+    }
+
+    /// Whether account_id may receive tokens from nft_buy, true for series without an allowlist
+    pub fn nft_is_allowlisted(&self, token_series_id: TokenSeriesId, account_id: ValidAccountId) -> bool {
+        assert!(self.token_series_by_id.get(&token_series_id).is_some(), "Token series not exist");
+        self.allowlist_by_series
+            .get(&token_series_id)
+            .map(|allowlist| allowlist.contains(account_id.as_ref()))
+            .unwrap_or(true)
+    }
+
+    /// Totals kept since creator stats were introduced, earlier series and sales are not counted
+    pub fn nft_creator_stats(&self, creator_id: ValidAccountId) -> CreatorStats {
+        let totals = self.creator_totals.get(creator_id.as_ref()).unwrap_or_default();
+        CreatorStats {
+            series_count: totals.series_count.into(),
+            total_minted: totals.total_minted.into(),
+            total_volume: totals.total_volume.into(),
+            pending_payout: self.pending_payouts.get(creator_id.as_ref()).unwrap_or(0).into(),
+        }
+    }
+
+    /// Royalty bps that can still be added to the series before hitting MAX_ROYALTY_BPS
+    pub fn nft_series_royalty_headroom(&self, token_series_id: TokenSeriesId) -> u32 {
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        let assigned: u32 = token_series.royalty.values().sum();
+        MAX_ROYALTY_BPS.saturating_sub(assigned)
+    }
+
+    pub fn nft_series_media_type(&self, token_series_id: TokenSeriesId) -> Option<String> {
+        self.token_series_by_id.get(&token_series_id).expect("Token series not exist").media_type
+    }
+
+    pub fn nft_series_price_currency(&self, token_series_id: TokenSeriesId) -> Option<CurrencyInfo> {
+        self.token_series_by_id.get(&token_series_id).expect("Token series not exist").price_currency
+    }
+
+    /// Series icon, falls back to the contract metadata icon
+    pub fn nft_series_icon(&self, token_series_id: TokenSeriesId) -> Option<String> {
+        self.token_series_by_id.get(&token_series_id).expect("Token series not exist").icon
+            .or_else(|| self.metadata.get().unwrap().icon)
+    }
+
+    /// Tokens of the series held by account_id, maintained on every ownership change.
+    /// Scans the account's tokens until backfill_owner_series_counts has finished.
+    pub fn nft_supply_for_owner_by_series(&self, account_id: ValidAccountId, token_series_id: TokenSeriesId) -> U64 {
+        if !self.owner_series_backfill_done {
+            let prefix = format!("{}{}", token_series_id, TOKEN_DELIMETER);
+            let count = self.tokens.tokens_per_owner
+                .as_ref()
+                .and_then(|tokens_per_owner| tokens_per_owner.get(account_id.as_ref()))
+                .map(|token_ids| token_ids.iter().filter(|token_id| token_id.starts_with(&prefix)).count())
+                .unwrap_or(0);
+            return U64(count as u64);
+        }
+        let key = (account_id.to_string(), token_series_id);
+        U64(self.count_by_owner_series.get(&key).unwrap_or(0) as u64)
+    }
+
+    pub fn nft_supply_for_series(&self, token_series_id: TokenSeriesId) -> U64 {
+        self.token_series_by_id.get(&token_series_id).expect("Token series not exist").tokens.len().into()
+    }
+
+    pub fn nft_next_token_id(&self, token_series_id: TokenSeriesId) -> Option<TokenId> {
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        let num_tokens = token_series.tokens.len();
+        let max_copies = token_series.metadata.copies.unwrap_or(u64::MAX);
+        if !token_series.mintable() || num_tokens >= max_copies {
+            return None;
+        }
+        let edition = token_series.first_free_edition(&token_series_id);
+        Some(format!("{}{}{}", &token_series_id, TOKEN_DELIMETER, edition))
+    }
+
+    pub fn nft_tokens_by_series(
+        &self,
+        token_series_id: TokenSeriesId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Token> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        let tokens = self.token_series_by_id.get(&token_series_id).unwrap().tokens;
+        assert!(
+            (tokens.len() as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        tokens
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|token_id| self.nft_token(token_id).unwrap())
+            .collect()
+    }
+
+    /// Series config with the first MAX_EXPORT_TOKENS owners, page the rest with nft_series_holders
+    pub fn nft_export_series(&self, token_series_id: TokenSeriesId) -> SeriesExport {
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        let supply = token_series.tokens.len();
+        let is_mintable = token_series.mintable();
+        let tokens = if supply > 0 {
+            self.nft_series_holders(token_series_id.clone(), None, Some(MAX_EXPORT_TOKENS))
+        } else {
+            vec![]
+        };
+        let next_index = if supply > MAX_EXPORT_TOKENS {
+            Some(U128::from(u128::from(MAX_EXPORT_TOKENS)))
+        } else {
+            None
+        };
+
+        SeriesExport {
+            token_series_id,
+            creator_id: token_series.creator_id,
+            metadata: token_series.metadata,
+            royalty: token_series.royalty,
+            price: token_series.price.map(U128),
+            is_mintable,
+            auto_approve: token_series.auto_approve,
+            royalty_decay: token_series.royalty_decay,
+            primary_split: token_series.primary_split,
+            charge_receiver_storage: token_series.charge_receiver_storage,
+            royalty_rounding: token_series.royalty_rounding,
+            burnable_by_owner: token_series.burnable_by_owner,
+            revealed: token_series.revealed,
+            revealed_metadata: token_series.revealed_metadata,
+            allow_custom_mint_metadata: token_series.allow_custom_mint_metadata,
+            series_contract_metadata: token_series.series_contract_metadata,
+            burn_on_transfer_reject: token_series.burn_on_transfer_reject,
+            max_sellable: token_series.max_sellable,
+            icon: token_series.icon,
+            price_currency: token_series.price_currency,
+            media_type: token_series.media_type,
+            token_lifetime_secs: token_series.token_lifetime_secs,
+            supply: supply.into(),
+            tokens,
+            next_index,
+        }
+    }
+
+    /// Recreates an exported series under the same id with the holders in export.tokens,
+    /// admin_import_series_holders adds the rest. issued_at (and expires_at) count from the import.
+    #[payable]
+    pub fn admin_import_series(&mut self, export: SeriesExport) -> TokenSeriesJson {
+        let initial_storage_usage = env::storage_usage();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        assert!(!self.retired, "Contract retired");
+        let token_series_id = export.token_series_id.clone();
+        assert!(
+            self.token_series_by_id.get(&token_series_id).is_none(),
+            "Token series already exists"
+        );
+        if let Ok(imported_id) = token_series_id.parse::<u64>() {
+            // keep later nft_create_series ids clear of the imported one
+            self.next_series_id = self.next_series_id.max(imported_id + 1);
+        }
+        assert!(is_valid_account_id(export.creator_id.as_bytes()), "Not valid account_id for creator");
+        assert_valid_royalty(&export.royalty);
+
+        self.token_series_by_id.insert(&token_series_id, &TokenSeries {
+            metadata: export.metadata.clone(),
+            creator_id: export.creator_id.clone(),
+            tokens: UnorderedSet::new(
+                StorageKey::TokensBySeriesInner {
+                    token_series: token_series_id.clone(),
+                }
+                .try_to_vec()
+                .unwrap(),
+            ),
+            price: export.price.map(|price| price.0),
+            is_mintable: export.is_mintable,
+            royalty: export.royalty.clone(),
+            auto_approve: export.auto_approve,
+            royalty_decay: export.royalty_decay,
+            primary_split: export.primary_split,
+            charge_receiver_storage: export.charge_receiver_storage,
+            royalty_rounding: export.royalty_rounding,
+            burnable_by_owner: export.burnable_by_owner,
+            next_edition: 1,
+            revealed: export.revealed,
+            revealed_metadata: export.revealed_metadata,
+            non_mintable_at: None,
+            allow_custom_mint_metadata: export.allow_custom_mint_metadata,
+            series_contract_metadata: export.series_contract_metadata,
+            burn_on_transfer_reject: export.burn_on_transfer_reject,
+            max_sellable: export.max_sellable,
+            icon: export.icon,
+            price_currency: export.price_currency,
+            media_type: export.media_type,
+            token_lifetime_secs: export.token_lifetime_secs,
+        });
+        let mut creator_totals = self.creator_totals.get(&export.creator_id).unwrap_or_default();
+        creator_totals.series_count += 1;
+        self.creator_totals.insert(&export.creator_id, &creator_totals);
+
+        self._import_holders(&token_series_id, export.tokens);
+        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
+
+        self.nft_get_series_single(token_series_id)
+    }
+
+    /// Restores another page of holders of a series recreated by admin_import_series
+    #[payable]
+    pub fn admin_import_series_holders(&mut self, token_series_id: TokenSeriesId, holders: Vec<(TokenId, AccountId)>) {
+        let initial_storage_usage = env::storage_usage();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        assert!(!self.retired, "Contract retired");
+        assert!(
+            holders.len() as u64 <= MAX_EXPORT_TOKENS,
+            "Cannot import more than {} holders in one call",
+            MAX_EXPORT_TOKENS
+        );
+
+        self._import_holders(&token_series_id, holders);
+        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
+    }
+
+    /// Writes imported tokens directly, a restore is not a mint: no mint limits, holder cap or sold-out event
+    fn _import_holders(&mut self, token_series_id: &TokenSeriesId, holders: Vec<(TokenId, AccountId)>) {
+        let mut token_series = self.token_series_by_id.get(token_series_id).expect("Token series not exist");
+        let max_copies = token_series.metadata.copies.unwrap_or(u64::MAX);
+        let metadata = new_token_metadata(token_series.token_lifetime_secs);
+
+        let mut mint_data: Vec<NftMintData> = Vec::new();
+        for (token_id, owner_id) in holders {
+            let mut parts = token_id.split(TOKEN_DELIMETER);
+            assert_eq!(parts.next(), Some(token_series_id.as_str()), "Token not in series");
+            let edition: u64 = parts.next().and_then(|edition| edition.parse().ok()).expect("Invalid token_id");
+            assert!(edition >= 1 && edition <= max_copies, "Edition out of range");
+            assert!(is_valid_account_id(owner_id.as_bytes()), "Not valid account_id for owner");
+            assert!(token_series.tokens.insert(&token_id), "Edition already minted");
+            token_series.next_edition = token_series.next_edition.max(edition + 1);
+
+            if self.holders.insert(&owner_id) {
+                self.distinct_owners += 1;
+            }
+            self.tokens.owner_by_id.insert(&token_id, &owner_id);
+            if let Some(token_metadata_by_id) = &mut self.tokens.token_metadata_by_id {
+                token_metadata_by_id.insert(&token_id, &metadata);
+            }
+            if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
+                let mut token_ids = tokens_per_owner.get(&owner_id).unwrap_or_else(|| {
+                    UnorderedSet::new(StorageKey::TokensPerOwner {
+                        account_hash: env::sha256(owner_id.as_bytes()),
+                    })
+                });
+                token_ids.insert(&token_id);
+                tokens_per_owner.insert(&owner_id, &token_ids);
+            }
+            self._record_ownership_change(&token_id, None, Some(owner_id.clone()));
+            mint_data.push(NftMintData {
+                owner_id,
+                token_ids: vec![token_id],
+                memo: None,
+            });
+        }
+
+        self.total_minted += mint_data.len() as u128;
+        let mut creator_totals = self.creator_totals.get(&token_series.creator_id).unwrap_or_default();
+        creator_totals.total_minted += mint_data.len() as u64;
+        self.creator_totals.insert(&token_series.creator_id, &creator_totals);
+
+        if token_series.tokens.len() >= max_copies {
+            token_series.is_mintable = false;
+        }
+        self.token_series_by_id.insert(token_series_id, &token_series);
+
+        if !mint_data.is_empty() {
+            NearEvent::log_nft_mints(mint_data);
+        }
+    }
+
+    /// Token owners of a series without assembling token metadata, for snapshots.
+    /// Burned editions are skipped, so a page can be shorter than limit: continue from from_index + limit.
+    pub fn nft_series_holders(
+        &self,
+        token_series_id: TokenSeriesId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<(TokenId, AccountId)> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        let tokens = self.token_series_by_id.get(&token_series_id).expect("Token series not exist").tokens;
+        assert!(
+            (tokens.len() as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        tokens
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .filter_map(|token_id| {
+                let owner_id = self.tokens.owner_by_id.get(&token_id)?;
+                Some((token_id, owner_id))
+            })
+            .collect()
+    }
+
+    /// Distinct series the account holds at least one token of, sorted by series id
+    pub fn nft_series_for_owner(
+        &self,
+        account_id: ValidAccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<TokenSeriesId> {
+        let token_set = if let Some(token_set) = self._tokens_for_owner_set(account_id.as_ref()) {
+            token_set
+        } else {
+            return vec![];
+        };
+
+        let mut token_series_ids: Vec<TokenSeriesId> = token_set
+            .iter()
+            .map(|token_id| token_id.split(TOKEN_DELIMETER).next().unwrap().to_string())
+            .collect();
+        token_series_ids.sort_by_key(|token_series_id| token_series_id.parse::<u64>().unwrap());
+        token_series_ids.dedup();
+
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        assert!(
+            (token_series_ids.len() as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        token_series_ids
+            .into_iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .collect()
+    }
+
+    pub fn nft_token(&self, token_id: TokenId) -> Option<Token> {
+        let owner_id = self.tokens.owner_by_id.get(&token_id)?;
+        let approved_account_ids = self
+            .tokens
+            .approvals_by_id
+            .as_ref()
+            .and_then(|by_id| by_id.get(&token_id).or_else(|| Some(HashMap::new())));
+
+        // CUSTOM (switch metadata for the token_series metadata)
+        let mut token_id_iter = token_id.split(TOKEN_DELIMETER);
+        let token_series_id = token_id_iter.next().unwrap().parse().unwrap();
+        let series_metadata = self.token_series_by_id.get(&token_series_id).unwrap().metadata;
+
+        let mut token_metadata = self.tokens.token_metadata_by_id.as_ref().unwrap().get(&token_id).unwrap();
+
+        token_metadata.title = series_metadata.title;
+
+        token_metadata.description = series_metadata.description;
+
+        token_metadata.reference = series_metadata.reference;
+        token_metadata.media = series_metadata.media;
+        token_metadata.copies = series_metadata.copies;
+        // extra set by the buyer at mint overrides the series extra
+        token_metadata.extra = token_metadata.extra.or(series_metadata.extra);
+
+        if self.prepend_base_uri {
+            if let Some(base_uri) = self.metadata.get().unwrap().base_uri {
+                token_metadata.media = token_metadata.media.map(|media| with_base_uri(&base_uri, media));
+                token_metadata.reference = token_metadata.reference.map(|reference| with_base_uri(&base_uri, reference));
+            }
+        }
+
+        Some(Token {
+            token_id,
+            owner_id,
+            metadata: Some(token_metadata),
+            approved_account_ids,
+        })
+    }
+
+    // CUSTOM core standard repeated here because no macro below
+
+    /// Transfer state change without logging, callers emit the nft_transfer event
+    /// (batch methods can aggregate several tokens into one event).
+    /// Transfers to the current owner are rejected so no no-op event is ever emitted.
+    /// internal_transfer clears the token approvals, the new owner starts without any.
+    fn do_transfer(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        token_id: &TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) -> (AccountId, Option<HashMap<AccountId, u64>>) {
+        self._assert_not_locked(token_id);
+        let owner_id = self.tokens.owner_by_id.get(token_id).expect("Token not found");
+        assert_ne!(&owner_id, receiver_id, "Cannot transfer to self");
+        assert!(!self._is_expired(token_id), "Token expired");
+        self._track_holder(receiver_id);
+        if self._is_in_revert_window(token_id) {
+            self.transferred_in_revert_window.insert(token_id);
+        }
+        self._record_ownership_change(token_id, Some(owner_id), Some(receiver_id.clone()));
+        self.tokens.internal_transfer(sender_id, receiver_id, token_id, approval_id, memo)
+    }
+
+    fn _record_ownership_change(&mut self, token_id: &TokenId, from: Option<AccountId>, to: Option<AccountId>) {
+        let token_series_id: TokenSeriesId = token_id.split(TOKEN_DELIMETER).next().unwrap().to_string();
+        // tokens past the backfill cursor are counted with their owner once the backfill reaches them
+        let counted = self.owner_series_backfill_done
+            || self.owner_series_backfill_cursor.as_ref().map(|cursor| token_id <= cursor).unwrap_or(false);
+        if let (true, Some(from)) = (counted, &from) {
+            let key = (from.clone(), token_series_id.clone());
+            let count = self.count_by_owner_series.get(&key).unwrap_or(0).saturating_sub(1);
+            if count == 0 {
+                self.count_by_owner_series.remove(&key);
+            } else {
+                self.count_by_owner_series.insert(&key, &count);
+            }
+        }
+        if let (true, Some(to)) = (counted, &to) {
+            let key = (to.clone(), token_series_id);
+            let count = self.count_by_owner_series.get(&key).unwrap_or(0) + 1;
+            self.count_by_owner_series.insert(&key, &count);
+        }
+
+        let record = ChangeRecord {
+            token_id: token_id.clone(),
+            from,
+            to,
+            block_height: U64(env::block_index()),
+        };
+        if self.ownership_changes.len() < OWNERSHIP_CHANGES_CAP {
+            self.ownership_changes.push(&record);
+        } else {
+            self.ownership_changes.replace(self.ownership_changes_total % OWNERSHIP_CHANGES_CAP, &record);
+        }
+        self.ownership_changes_total += 1;
+    }
+
+    fn _track_holder(&mut self, account_id: &AccountId) {
+        if self.holders.contains(account_id) {
+            return;
+        }
+        if let Some(max_distinct_owners) = self.max_distinct_owners {
+            assert!(self.distinct_owners < max_distinct_owners, "Holder cap reached");
+        }
+        self.holders.insert(account_id);
+        self.distinct_owners += 1;
+    }
+
+    pub fn nft_transfer_unsafe(
+        &mut self,
+        receiver_id: ValidAccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        let sender_id = env::predecessor_account_id();
+        let receiver_id_str = receiver_id.to_string();
+        let (previous_owner_id, _) = self.do_transfer(&sender_id, &receiver_id_str, &token_id, approval_id, memo.clone());
+
+        NearEvent::log_nft_transfer(
+            previous_owner_id.clone(),
+            receiver_id_str,
+            vec![token_id],
+            memo,
+            authorized_id(sender_id, &previous_owner_id),
+        );
+    }
+
+    #[payable]
+    pub fn nft_transfer(
+        &mut self,
+        receiver_id: ValidAccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        assert_one_yocto();
+        let sender_id = env::predecessor_account_id();
+        let receiver_id_str = receiver_id.to_string();
+        let (previous_owner_id, _) = self.do_transfer(&sender_id, &receiver_id_str, &token_id, approval_id, memo.clone());
+
+        NearEvent::log_nft_transfer(
+            previous_owner_id.clone(),
+            receiver_id_str,
+            vec![token_id],
+            memo,
+            authorized_id(sender_id, &previous_owner_id),
+        );
+    }
+
+    #[payable]
+    pub fn nft_transfer_call(
+        &mut self,
+        receiver_id: ValidAccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        assert_one_yocto();
+        let sender_id = env::predecessor_account_id();
+        let (previous_owner_id, old_approvals) = self.do_transfer(
+            &sender_id,
+            receiver_id.as_ref(),
+            &token_id,
+            approval_id,
+            memo.clone(),
+        );
+
+        NearEvent::log_nft_transfer(
+            previous_owner_id.clone(),
+            receiver_id.to_string(),
+            vec![token_id.clone()],
+            memo,
+            authorized_id(sender_id.clone(), &previous_owner_id),
+        );
+
+        // Initiating receiver's call and the callback
+        ext_non_fungible_token_receiver::nft_on_transfer(
+            sender_id,
+            previous_owner_id.clone(),
+            token_id.clone(),
+            msg,
+            receiver_id.as_ref(),
+            NO_DEPOSIT,
+            env::prepaid_gas() - GAS_FOR_NFT_TRANSFER_CALL,
+        )
+        .then(ext_self::nft_resolve_transfer(
+            previous_owner_id,
+            receiver_id.into(),
+            token_id,
+            old_approvals,
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_TRANSFER,
+        ))
+        .into()
+    }
+
+    #[payable]
+    pub fn nft_list_on_market(
+        &mut self,
+        receiver_id: ValidAccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        listing: ListingArgs,
+    ) -> PromiseOrValue<bool> {
+        let msg = near_sdk::serde_json::to_string(&listing).unwrap();
+        self.nft_transfer_call(receiver_id, token_id, approval_id, None, msg)
+    }
+
+    // CUSTOM enumeration standard modified here because no macro below
+
+    pub fn nft_total_supply(&self) -> U128 {
+        (self.tokens.owner_by_id.len() as u128).into()
+    }
+
+    pub fn nft_total_minted(&self) -> U128 {
+        self.total_minted.into()
+    }
+
+    pub fn nft_total_burned(&self) -> U128 {
+        self.total_burned.into()
+    }
+
+    pub fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        // Get starting index, whether or not it was explicitly given.
+        // Defaults to 0 based on the spec:
+        // https://nomicon.io/Standards/NonFungibleToken/Enumeration.html#interface
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        assert!(
+            (self.tokens.owner_by_id.len() as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+        self.tokens
+            .owner_by_id
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|(token_id, _)| self.nft_token(token_id).unwrap())
+            .collect()
+    }
+
+    /// Tokens whose issued_at (block timestamp in nanoseconds) is in [from_ts, to_ts).
+    /// Scans limit tokens from from_index, meant for indexer backfill and not for hot paths.
+    pub fn nft_tokens_minted_between(
+        &self,
+        from_ts: U64,
+        to_ts: U64,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Token> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        assert!(
+            (self.tokens.owner_by_id.len() as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        let token_metadata_by_id = self.tokens.token_metadata_by_id.as_ref().unwrap();
+        self.tokens
+            .owner_by_id
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .filter(|(token_id, _)| {
+                token_metadata_by_id
+                    .get(token_id)
+                    .and_then(|metadata| metadata.issued_at)
+                    .and_then(|issued_at| issued_at.parse::<u64>().ok())
+                    .map(|issued_at| issued_at >= from_ts.0 && issued_at < to_ts.0)
+                    .unwrap_or(false)
+            })
+            .map(|(token_id, _)| self.nft_token(token_id).unwrap())
+            .collect()
+    }
+
+    /// Ownership changes at or after block_height, oldest first.
+    /// Only the latest OWNERSHIP_CHANGES_CAP changes are kept, from_index counts from the oldest kept one.
+    pub fn nft_ownership_changes_since(
+        &self,
+        block_height: U64,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<ChangeRecord> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        let len = self.ownership_changes.len();
+        assert!(
+            (len as u128) > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        let oldest = self.ownership_changes_total - len;
+        (oldest..self.ownership_changes_total)
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|index| self.ownership_changes.get(index % OWNERSHIP_CHANGES_CAP).unwrap())
+            .filter(|record| record.block_height.0 >= block_height.0)
+            .collect()
+    }
+
+    pub fn nft_supply_for_owner(self, account_id: ValidAccountId) -> U128 {
+        self._tokens_for_owner_set(account_id.as_ref())
+            .map(|account_tokens| U128::from(account_tokens.len() as u128))
+            .unwrap_or(U128(0))
+    }
+
+    /// Whether the per-owner token index exists. Without it the owner based views
+    /// (nft_tokens_for_owner, nft_supply_for_owner, ...) return empty results.
+    pub fn enumeration_enabled(&self) -> bool {
+        self.tokens.tokens_per_owner.is_some()
+    }
+
+    /// None when the owner holds nothing or enumeration is not enabled
+    fn _tokens_for_owner_set(&self, account_id: &AccountId) -> Option<UnorderedSet<TokenId>> {
+        self.tokens.tokens_per_owner.as_ref()?.get(account_id)
+    }
+
+    pub fn nft_tokens_for_owner(
+        &self,
+        account_id: ValidAccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Token> {
+        let token_set = if let Some(token_set) = self._tokens_for_owner_set(account_id.as_ref()) {
+            token_set
+        } else {
+            return vec![];
+        };
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        assert!(
+            token_set.len() as u128 > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        token_set
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|token_id| self.nft_token(token_id).unwrap())
+            .collect()
+    }
+
+    /// nft_tokens_for_owner ordered by series then edition, stable across pages while the set changes
+    pub fn nft_tokens_for_owner_sorted(
+        &self,
+        account_id: ValidAccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Token> {
+        let token_set = if let Some(token_set) = self._tokens_for_owner_set(account_id.as_ref()) {
+            token_set
+        } else {
+            return vec![];
+        };
+        assert!(
+            token_set.len() <= MAX_SORTED_TOKENS_FOR_OWNER,
+            "Owner holds more than {} tokens, use nft_tokens_for_owner",
+            MAX_SORTED_TOKENS_FOR_OWNER
+        );
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        assert!(
+            token_set.len() as u128 > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+
+        let mut token_ids = token_set.to_vec();
+        token_ids.sort_by_key(|token_id| token_id_sort_key(token_id));
+        token_ids
+            .into_iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|token_id| self.nft_token(token_id).unwrap())
+            .collect()
+    }
+
+    /// Scans a page of the owner's tokens and returns those approved for approved_id
+    pub fn nft_tokens_approved_for(
+        &self,
+        account_id: ValidAccountId,
+        approved_id: ValidAccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<TokenId> {
+        let token_set = if let Some(token_set) = self._tokens_for_owner_set(account_id.as_ref()) {
+            token_set
+        } else {
+            return vec![];
+        };
+        let approvals_by_id = if let Some(approvals_by_id) = &self.tokens.approvals_by_id {
+            approvals_by_id
+        } else {
+            return vec![];
+        };
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        assert!(
+            token_set.len() as u128 > start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+
+        token_set
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .filter(|token_id| {
+                approvals_by_id
+                    .get(token_id)
+                    .map(|approvals| approvals.contains_key(approved_id.as_ref()))
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    pub fn nft_payout(
+        &self, 
+        token_id: TokenId,
+        balance: U128, 
+        max_len_payout: u32
+    ) -> Payout{
+        let owner_id = self.tokens.owner_by_id.get(&token_id).expect("No token id");
+        let royalty = self._nft_payout_royalty(&token_id);
+
+        assert!(royalty.len() as u32 <= max_len_payout, "Market cannot payout to that many receivers");
+
+        let rounding = self._nft_royalty_rounding(&token_id);
+        compute_payout(&royalty, &owner_id, balance.into(), rounding)
+    }
+
+    /// nft_payout for each (token_ids[i], balances[i]) pair of a bundle sale
+    pub fn nft_payout_batch(
+        &self,
+        token_ids: Vec<TokenId>,
+        balances: Vec<U128>,
+        max_len_payout: u32,
+    ) -> Vec<Payout> {
+        assert_eq!(token_ids.len(), balances.len(), "Token ids and balances length mismatch");
+        assert!(
+            token_ids.len() <= MAX_BATCH_PAYOUT,
+            "Cannot compute more than {} payouts in one batch",
+            MAX_BATCH_PAYOUT
+        );
+        token_ids
+            .into_iter()
+            .zip(balances)
+            .map(|(token_id, balance)| self.nft_payout(token_id, balance, max_len_payout))
+            .collect()
+    }
+
+    /// nft_payout dry run returning the failure reason instead of panicking
+    pub fn nft_payout_safe(
+        &self,
+        token_id: TokenId,
+        balance: U128,
+        max_len_payout: u32
+    ) -> PayoutResult {
+        let owner_id = match self.tokens.owner_by_id.get(&token_id) {
+            Some(owner_id) => owner_id,
+            None => return PayoutResult { ok: false, payout: None, error: Some("No token id".to_string()) },
+        };
+        let royalty = self._nft_payout_royalty(&token_id);
+        if royalty.len() as u32 > max_len_payout {
+            return PayoutResult {
+                ok: false,
+                payout: None,
+                error: Some("Market cannot payout to that many receivers".to_string()),
+            };
+        }
+
+        let rounding = self._nft_royalty_rounding(&token_id);
+        PayoutResult {
+            ok: true,
+            payout: Some(compute_payout(&royalty, &owner_id, balance.into(), rounding)),
+            error: None,
+        }
+    }
+
+    #[payable]
+    pub fn nft_transfer_payout(
+        &mut self, 
+        receiver_id: ValidAccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        balance: Option<U128>,
+        max_len_payout: Option<u32>
+    ) -> Option<Payout> {
+        assert_one_yocto();
+
+        let sender_id = env::predecessor_account_id();
+        // Transfer
+        let (previous_owner_id, _) = self.do_transfer(&sender_id, receiver_id.as_ref(), &token_id, approval_id, None);
+
+        // Payout calculation
+        let payout = if let Some(balance) = balance {
+            let royalty = self._nft_payout_royalty(&token_id);
+            let rounding = self._nft_royalty_rounding(&token_id);
+            self._nft_record_resale(&token_id);
+
+            assert!(royalty.len() as u32 <= max_len_payout.unwrap(), "Market cannot payout to that many receivers");
+            Some(compute_payout(&royalty, &previous_owner_id, balance.into(), rounding))
+        } else {
+            None
+        };
+
+        NearEvent::log_nft_transfer(
+            previous_owner_id.clone(),
+            receiver_id.to_string(),
+            vec![token_id],
+            None,
+            authorized_id(sender_id, &previous_owner_id),
+        );
+
+        payout
+    }
+
+    /// Gas nft_transfer_call keeps for itself and nft_resolve_transfer,
+    /// the rest of the prepaid gas goes to the receiver's nft_on_transfer
+    pub fn nft_transfer_call_min_gas(&self) -> U64 {
+        U64(GAS_FOR_NFT_TRANSFER_CALL)
+    }
+
+    /// Paid sales, editions minted (burned ones included) and live tokens of the series
+    pub fn nft_series_sales(&self, token_series_id: TokenSeriesId) -> SeriesSales {
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("Token series not exist");
+        let minted_count = token_series.tokens.len();
+        let burned = self.burned_by_series.get(&token_series_id).unwrap_or(0);
+        SeriesSales {
+            total_sold: self.sold_by_series.get(&token_series_id).unwrap_or(0).into(),
+            minted_count: minted_count.into(),
+            supply: minted_count.saturating_sub(burned).into(),
+        }
+    }
+
+    pub fn nft_series_volume(&self, token_series_id: TokenSeriesId) -> U128 {
+        self.volume_by_series.get(&token_series_id).unwrap_or(0).into()
+    }
+
+    /// Whether the account ever minted from the series, regardless of current holdings
+    pub fn nft_has_minted(&self, account_id: ValidAccountId, token_series_id: TokenSeriesId) -> bool {
+        let account_id: AccountId = account_id.into();
+        self.minted_per_owner
+            .contains_key(&format!("{}{}{}", token_series_id, TOKEN_DELIMETER, account_id))
+    }
+
+    pub fn nft_resale_count(&self, token_id: TokenId) -> u32 {
+        self.resale_count_by_token.get(&token_id).unwrap_or(0)
+    }
+
+    /// Total royalty bps of a token (EIP-2981 style), capped at 10000
+    pub fn royalty_bps(&self, token_id: TokenId) -> u32 {
+        let total: u32 = self._nft_royalty(&token_id).values().sum();
+        total.min(10_000)
+    }
+
+    /// Token royalty override, falling back to the series royalty, with the resale decay applied
+    fn _nft_royalty(&self, token_id: &TokenId) -> HashMap<AccountId, u32> {
+        let mut token_id_iter = token_id.split(TOKEN_DELIMETER);
+        let token_series_id: TokenSeriesId = token_id_iter.next().unwrap().parse().unwrap();
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("no type");
+        let royalty = self.token_royalty_override.get(token_id).unwrap_or(token_series.royalty);
+
+        if token_series.royalty_decay.is_empty() {
+            return royalty;
+        }
+
+        let resale_count = self.resale_count_by_token.get(token_id).unwrap_or(0) as usize;
+        let step = resale_count.min(token_series.royalty_decay.len() - 1);
+        let decay = token_series.royalty_decay[step];
+
+        royalty
+            .into_iter()
+            .map(|(account_id, bps)| (account_id, bps * decay / 10_000))
+            .collect()
+    }
+
+    /// Token royalty with the secondary treasury cut as an extra recipient
+    fn _nft_payout_royalty(&self, token_id: &TokenId) -> HashMap<AccountId, u32> {
+        let mut royalty = self._nft_royalty(token_id);
+        if self.secondary_treasury_bps > 0 {
+            *royalty.entry(self.secondary_treasury_id.clone()).or_insert(0) += self.secondary_treasury_bps;
+        }
+        royalty
+    }
+
+    fn _nft_royalty_rounding(&self, token_id: &TokenId) -> RoundingMode {
+        let token_series_id: TokenSeriesId = token_id.split(TOKEN_DELIMETER).next().unwrap().to_string();
+        self.token_series_by_id.get(&token_series_id).expect("no type").royalty_rounding
+    }
+
+    fn _nft_record_resale(&mut self, token_id: &TokenId) {
+        let token_series_id: TokenSeriesId = token_id.split(TOKEN_DELIMETER).next().unwrap().to_string();
+        let token_series = self.token_series_by_id.get(&token_series_id).expect("no type");
+
+        // only decaying series need the count
+        if !token_series.royalty_decay.is_empty() {
+            let resale_count = self.resale_count_by_token.get(token_id).unwrap_or(0);
+            self.resale_count_by_token.insert(token_id, &(resale_count + 1));
+        }
+    }
+
+    /// Keeps the treasury share of a sale for the owner when the treasury transfer fails
+    #[private]
+    pub fn on_treasury_transfer_failed(&mut self, amount: U128) {
+        if let PromiseResult::Failed = env::promise_result(0) {
+            let owner_id = self.tokens.owner_id.clone();
+            let pending_payout = self.pending_payouts.get(&owner_id).unwrap_or(0) + amount.0;
+            self.pending_payouts.insert(&owner_id, &pending_payout);
+
+            env::log(
+                json!({
+                    "type": "treasury_transfer_failed",
+                    "params": {
+                        "treasury_id": self.primary_treasury_id,
+                        "amount": amount,
+                        "pending_payout_to": owner_id,
+                    }
+                })
+                .to_string()
+                .as_bytes(),
+            );
+        }
+    }
+
+    /// Releases the vested part of the caller's primary sale proceeds
+    #[payable]
+    pub fn withdraw_vested(&mut self) -> U128 {
+        assert_one_yocto();
+        assert!(!self.withdrawals_paused, "Withdrawals paused");
+        let account_id = env::predecessor_account_id();
+        let mut grants = self.vesting_balances.get(&account_id).expect("No vesting balance");
+
+        let now = to_sec(env::block_timestamp());
+        let mut amount: Balance = 0;
+        for grant in grants.iter_mut() {
+            let vested = grant.vested(now);
+            amount += vested - grant.released;
+            grant.released = vested;
+        }
+        assert!(amount > 0, "Nothing vested yet");
+
+        grants.retain(|grant| grant.released < grant.amount);
+        if grants.is_empty() {
+            self.vesting_balances.remove(&account_id);
+        } else {
+            self.vesting_balances.insert(&account_id, &grants);
+        }
+        Promise::new(account_id).transfer(amount);
+        amount.into()
+    }
+
+    pub fn vested_available(&self, account_id: ValidAccountId) -> U128 {
+        let now = to_sec(env::block_timestamp());
+        self.vesting_balances
+            .get(account_id.as_ref())
+            .map(|grants| grants.iter().map(|grant| grant.vested(now) - grant.released).sum())
+            .unwrap_or(0)
+            .into()
+    }
+
+    /// Pays a primary sale share now, or holds it under the vesting schedule
+    fn _pay_primary_share(&mut self, account_id: AccountId, amount: Balance) {
+        if let Some(vesting) = self.vesting {
+            // the start is always ahead, so a grant still open for merging has released nothing
+            let start = (to_sec(env::block_timestamp()) / VESTING_BUCKET_SECS + 1) * VESTING_BUCKET_SECS;
+            let mut grants = self.vesting_balances.get(&account_id).unwrap_or_default();
+            match grants.last_mut() {
+                Some(grant) if grant.start == start && grant.vesting == vesting => grant.amount += amount,
+                _ => grants.push(VestingGrant {
+                    amount,
+                    released: 0,
+                    start,
+                    vesting,
+                }),
+            }
+            self.vesting_balances.insert(&account_id, &grants);
+        } else {
+            Promise::new(account_id).transfer(amount);
+        }
+    }
+
+    #[payable]
+    pub fn claim_pending_payout(&mut self) -> U128 {
+        assert_one_yocto();
+        assert!(!self.withdrawals_paused, "Withdrawals paused");
+        let account_id = env::predecessor_account_id();
+        let amount = self.pending_payouts.remove(&account_id).expect("No pending payout");
+        Promise::new(account_id).transfer(amount);
+        amount.into()
+    }
+
+    pub fn get_pending_payout(&self, account_id: ValidAccountId) -> U128 {
+        self.pending_payouts.get(account_id.as_ref()).unwrap_or(0).into()
+    }
+
+    // Circuit breaker for payout withdrawals, pending payouts keep accruing and mints are unaffected
+    #[payable]
+    pub fn set_withdrawals_paused(&mut self, withdrawals_paused: bool) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        self.withdrawals_paused = withdrawals_paused;
+    }
+
+    pub fn get_withdrawals_paused(&self) -> bool {
+        self.withdrawals_paused
+    }
+
+    // Retirement, first step. retire() becomes callable after RETIREMENT_TIMELOCK
+    #[payable]
+    pub fn request_retirement(&mut self) -> TimestampSec {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        assert!(!self.retired, "Contract retired");
+        assert!(self.retirement_available_at.is_none(), "Retirement already requested");
+
+        let retirement_available_at = to_sec(env::block_timestamp()) + RETIREMENT_TIMELOCK;
+        self.retirement_available_at = Some(retirement_available_at);
+        env::log(
+            json!({
+                "type": "request_retirement",
+                "params": {
+                    "retirement_available_at": retirement_available_at,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+        retirement_available_at
+    }
+
+    #[payable]
+    pub fn cancel_retirement(&mut self) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        assert!(!self.retired, "Contract retired");
+        assert!(self.retirement_available_at.is_some(), "Retirement not requested");
+        self.retirement_available_at = None;
+        env::log(
+            json!({
+                "type": "cancel_retirement",
+                "params": {}
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    /// Permanently disables minting and series creation. Transfers, burns and
+    /// payout withdrawals keep working so holders can exit. Cannot be undone.
+    #[payable]
+    pub fn retire(&mut self) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        assert!(!self.retired, "Contract retired");
+        let retirement_available_at = self.retirement_available_at.expect("Retirement not requested");
+        assert!(
+            to_sec(env::block_timestamp()) >= retirement_available_at,
+            "Retirement timelock active"
+        );
+
+        self.retired = true;
+        env::log(
+            json!({
+                "type": "retire",
+                "params": {}
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    pub fn is_retired(&self) -> bool {
+        self.retired
+    }
+
+    pub fn get_retirement_available_at(&self) -> Option<TimestampSec> {
+        self.retirement_available_at
+    }
+
+    pub fn batch_limits(&self) -> BatchLimits {
+        BatchLimits {
+            mint: MAX_BATCH_MINT as u32,
+            burn: MAX_BATCH_BURN as u32,
+            transfer: MAX_BATCH_TRANSFER as u32,
+            approve: MAX_BATCH_APPROVE as u32,
+            payout: MAX_BATCH_PAYOUT as u32,
+            set_non_mintable: MAX_BATCH_SET_NON_MINTABLE as u32,
+        }
+    }
+
+    // Contract icon, also the fallback for series without their own icon
+    #[payable]
+    pub fn set_contract_icon(&mut self, icon: Option<String>) {
+        let initial_storage_usage = env::storage_usage();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Owner only"
+        );
+        if let Some(icon) = &icon {
+            assert!(
+                icon.len() <= MAX_SERIES_ICON_LEN,
+                "Icon exceeds {} bytes",
+                MAX_SERIES_ICON_LEN
+            );
+        }
+        let mut metadata = self.metadata.get().unwrap();
+        metadata.icon = icon;
+        self.metadata.set(&metadata);
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage), 0);
+    }
+
+    pub fn get_owner(&self) -> AccountId {
+        self.tokens.owner_id.clone()
+    }
+}
+
+/// Splits balance between royalty recipients and the owner. An owner listed in the
+/// royalty receives it as part of the residual, which also absorbs rounding so the
+/// payout always sums to balance.
+fn compute_payout(
+    royalty: &HashMap<AccountId, u32>,
+    owner_id: &AccountId,
+    balance: Balance,
+    rounding: RoundingMode,
+) -> Payout {
+    let mut payout: Payout = Payout { payout: HashMap::new() };
+    let mut total_perpetual = 0;
+    let mut total_paid: Balance = 0;
+
+    for (k, v) in royalty.iter() {
+        if k != owner_id {
+            let amount = royalty_to_payout(*v, balance, rounding).0.min(balance - total_paid);
+            payout.payout.insert(k.clone(), U128(amount));
+            total_perpetual += *v;
+            total_paid += amount;
+        }
+    }
+    assert!(
+        total_perpetual <= 10000,
+        "Total payout overflow"
+    );
+
+    payout.payout.insert(owner_id.clone(), U128(balance - total_paid));
+    payout
+}
+
+fn royalty_to_payout(a: u32, b: Balance, rounding: RoundingMode) -> U128 {
+    let amount = (a as u128)
+        .checked_mul(b)
+        .expect("Royalty payout overflow");
+    let amount = match rounding {
+        RoundingMode::Floor => amount,
+        RoundingMode::Round => amount.checked_add(5_000).expect("Royalty payout overflow"),
+        RoundingMode::Ceil => amount.checked_add(9_999).expect("Royalty payout overflow"),
+    };
+    U128(amount / 10_000u128)
+}
+
+// near_contract_standards::impl_non_fungible_token_core!(Contract, tokens);
+// near_contract_standards::impl_non_fungible_token_enumeration!(Contract, tokens);
+// near_contract_standards::impl_non_fungible_token_approval!(Contract, tokens);
+
+#[near_bindgen]
+impl NonFungibleTokenApproval for Contract {
+    #[payable]
+    fn nft_approve(
+        &mut self,
+        token_id: TokenId,
+        account_id: ValidAccountId,
+        msg: Option<String>,
+    ) -> Option<Promise> {
+        self._assert_approvals_within_limit(&token_id, account_id.as_ref());
+        self.tokens.nft_approve(token_id, account_id, msg)
+    }
+
+    #[payable]
+    fn nft_revoke(&mut self, token_id: TokenId, account_id: ValidAccountId) {
+        self.tokens.nft_revoke(token_id, account_id)
+    }
+
+    #[payable]
+    fn nft_revoke_all(&mut self, token_id: TokenId) {
+        self.tokens.nft_revoke_all(token_id)
+    }
+
+    fn nft_is_approved(
+        self,
+        token_id: TokenId,
+        approved_account_id: ValidAccountId,
+        approval_id: Option<u64>,
+    ) -> bool {
+        self.tokens.nft_is_approved(token_id, approved_account_id, approval_id)
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenMetadataProvider for Contract {
+    fn nft_metadata(&self) -> NFTContractMetadata {
+        self.metadata.get().unwrap()
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenResolver for Contract {
+    #[private]
+    fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approved_account_ids: Option<HashMap<AccountId, u64>>,
+    ) -> bool {
+        let resp: bool = self.tokens.nft_resolve_transfer(
+            previous_owner_id.clone(),
+            receiver_id.clone(),
+            token_id.clone(),
+            approved_account_ids,
+        );
+
+        // if not successful, return nft back to original owner
+        if !resp {
+            self._record_ownership_change(&token_id, Some(receiver_id.clone()), Some(previous_owner_id.clone()));
+            let token_series_id: TokenSeriesId = token_id.split(TOKEN_DELIMETER).next().unwrap().to_string();
+            let burn_on_transfer_reject = self.token_series_by_id.get(&token_series_id)
+                .map(|token_series| token_series.burn_on_transfer_reject)
+                .unwrap_or(false);
+            if burn_on_transfer_reject {
+                self._nft_burn(&token_id, &previous_owner_id);
+                NearEvent::log_nft_burn(
+                    previous_owner_id,
+                    vec![token_id],
+                    None,
+                    None,
+                );
+                return resp;
+            }
+
+            NearEvent::log_nft_transfer(
+                receiver_id,
+                previous_owner_id,
+                vec![token_id],
+                None,
+                None,
+            );
+        }
+
+        resp
+    }
+}
+
+/// sha256 of the series and the full receiver list, identifies one nft_batch_mint_range job
+fn batch_mint_cursor_key(token_series_id: &str, receiver_ids: &[ValidAccountId]) -> Vec<u8> {
+    let receivers: Vec<&str> = receiver_ids.iter().map(|receiver_id| receiver_id.as_ref().as_str()).collect();
+    env::sha256(format!("{}{}{}", token_series_id, TOKEN_DELIMETER, receivers.join(",")).as_bytes())
+}
+
+/// (series, edition) so "2:10" sorts after "2:9" and "10:1" after "9:1"
+fn token_id_sort_key(token_id: &str) -> (u64, u64) {
+    let mut parts = token_id.split(TOKEN_DELIMETER).map(|part| part.parse::<u64>().unwrap_or(u64::MAX));
+    (parts.next().unwrap_or(u64::MAX), parts.next().unwrap_or(u64::MAX))
+}
+
+/// approved sender acting on behalf of the owner, reported in nft_transfer events
+fn authorized_id(sender_id: AccountId, previous_owner_id: &str) -> Option<AccountId> {
+    if sender_id != previous_owner_id {
+        Some(sender_id)
+    } else {
+        None
+    }
+}
+
+/// first recipient takes the rounding dust so the split always adds up to amount
+fn split_primary_sale(primary_split: &[(AccountId, u32)], amount: Balance) -> Vec<(AccountId, Balance)> {
+    let mut shares: Vec<(AccountId, Balance)> = primary_split
+        .iter()
+        .map(|(account_id, bps)| (account_id.clone(), royalty_to_payout(*bps, amount, RoundingMode::Floor).0))
+        .collect();
+    let distributed: Balance = shares.iter().map(|(_, share)| share).sum();
+    if let Some(first) = shares.first_mut() {
+        first.1 += amount - distributed;
+    }
+    shares
+}
+
+/// Prefixes bare uris (e.g. IPFS CIDs) with base_uri, absolute uris are kept as is
+fn with_base_uri(base_uri: &str, uri: String) -> String {
+    if uri.starts_with("http") || uri.starts_with("ipfs://") || uri.starts_with(base_uri) {
+        uri
+    } else {
+        format!("{}{}", base_uri, uri)
+    }
+}
+
+/// from https://github.com/near/near-sdk-rs/blob/e4abb739ff953b06d718037aa1b8ab768db17348/near-contract-standards/src/non_fungible_token/utils.rs#L29
+
+/// Per-token metadata written on mint, the series metadata is merged in on read
+fn new_token_metadata(token_lifetime_secs: Option<u32>) -> TokenMetadata {
+    let expires_at = token_lifetime_secs
+        .map(|token_lifetime_secs| env::block_timestamp() + u64::from(token_lifetime_secs) * 10u64.pow(9));
+
+    TokenMetadata {
+        title: None,          // ex. "Arch Nemesis: Mail Carrier" or "Parcel #5055"
+        description: None,    // free-form description
+        media: None, // URL to associated media, preferably to decentralized, content-addressed storage
+        media_hash: None, // Base64-encoded sha256 hash of content referenced by the `media` field. Required if `media` is included.
+        copies: None, // number of copies of this set of metadata in existence when token was minted.
+        issued_at: Some(env::block_timestamp().to_string()), // ISO 8601 datetime when token was issued or minted
+        expires_at: expires_at.map(|expires_at| expires_at.to_string()), // ISO 8601 datetime when token expires
+        starts_at: None, // ISO 8601 datetime when token starts being valid
+        updated_at: None, // ISO 8601 datetime when token was last updated
+        extra: None, // anything extra the NFT wants to store on-chain. Can be stringified JSON.
+        reference: None, // URL to an off-chain JSON file with more info.
+        reference_hash: None, // Base64-encoded sha256 hash of JSON from reference field. Required if `reference` is included.
+    }
+}
+
+fn refund_deposit(storage_used: u64, extra_spend: Balance) {
+    refund_deposit_to(storage_used, extra_spend, env::predecessor_account_id());
+}
+
+fn refund_deposit_to(storage_used: u64, extra_spend: Balance, refund_to: AccountId) {
+    let required_cost = env::storage_byte_cost()
+        .checked_mul(Balance::from(storage_used))
+        .expect("Storage cost overflow");
+    let attached_deposit = env::attached_deposit()
+        .checked_sub(extra_spend)
+        .expect("Attached deposit is less than extra spend");
+
+    assert!(
+        required_cost <= attached_deposit,
+        "Must attach {} yoctoNEAR to cover storage",
+        required_cost,
+    );
+
+    let refund = attached_deposit - required_cost;
+    if refund > 1 {
+        Promise::new(refund_to).transfer(refund);
+    }
+}
+
+fn log_storage_deposit(account_id: &AccountId, amount: Balance, total: Balance) {
+    env::log(
+        json!({
+            "type": "storage_deposit",
+            "params": {
+                "account_id": account_id,
+                "amount": U128(amount),
+                "total": U128(total),
+            }
+        })
+        .to_string()
+        .as_bytes(),
+    );
+}
+
+fn log_storage_withdraw(account_id: &AccountId, amount: Balance, total: Balance) {
+    env::log(
+        json!({
+            "type": "storage_withdraw",
+            "params": {
+                "account_id": account_id,
+                "amount": U128(amount),
+                "total": U128(total),
+            }
+        })
+        .to_string()
+        .as_bytes(),
+    );
+}
+
+/// buyers can only personalize extra, everything else comes from the series
+fn assert_custom_mint_metadata(token_metadata: &TokenMetadata) {
+    assert!(
+        token_metadata.title.is_none()
+            && token_metadata.description.is_none()
+            && token_metadata.media.is_none()
+            && token_metadata.media_hash.is_none()
+            && token_metadata.copies.is_none()
+            && token_metadata.issued_at.is_none()
+            && token_metadata.expires_at.is_none()
+            && token_metadata.starts_at.is_none()
+            && token_metadata.updated_at.is_none()
+            && token_metadata.reference.is_none()
+            && token_metadata.reference_hash.is_none(),
+        "Only extra can be set in custom mint metadata"
+    );
+}
+
+/// at most 10 valid recipients sharing at most 50%
+fn assert_valid_royalty(royalty: &HashMap<AccountId, u32>) {
+    let mut total_perpetual = 0;
+    for (k, v) in royalty.iter() {
+        if !is_valid_account_id(k.as_bytes()) {
+            env::panic("Not valid account_id for royalty".as_bytes());
+        };
+        total_perpetual += *v;
+    }
+
+    assert!(royalty.len() <= 10, "Royalty exceeds 10 accounts");
+
+    assert!(
+        total_perpetual <= MAX_ROYALTY_BPS,
+        "Exceeds maximum royalty -> 50%",
+    );
+}
+
+/// implicit accounts are 64 lowercase hex chars derived from a key, nobody may hold that key
+fn assert_named_royalty_accounts(royalty: &HashMap<AccountId, u32>) {
+    for account_id in royalty.keys() {
+        let is_implicit = account_id.len() == 64
+            && account_id.bytes().all(|c| c.is_ascii_digit() || (b'a'..=b'f').contains(&c));
+        assert!(!is_implicit, "Royalty account must be a named account");
+    }
+}
+
+/// media_hash and reference_hash are required alongside their fields and must be sha256 (32 bytes)
+fn assert_metadata_hashes(token_metadata: &TokenMetadata) {
+    if token_metadata.media.is_some() {
+        let media_hash = token_metadata.media_hash.as_ref().expect("Token_metadata.media_hash is required");
+        assert_eq!(media_hash.0.len(), 32, "Token_metadata.media_hash has to be 32 bytes");
+    }
+
+    if token_metadata.reference.is_some() {
+        let reference_hash = token_metadata.reference_hash.as_ref().expect("Token_metadata.reference_hash is required");
+        assert_eq!(reference_hash.0.len(), 32, "Token_metadata.reference_hash has to be 32 bytes");
+    }
+}
+
+fn to_sec(timestamp: Timestamp) -> TimestampSec {
+    (timestamp / 10u64.pow(9)) as u32
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use near_sdk::json_types::Base64VecU8;
+    use near_sdk::test_utils::{accounts, get_created_receipts, get_logs, VMContextBuilder};
+    use near_sdk::{MockedBlockchain, RuntimeFeesConfig, VMConfig};
+    use near_sdk::{testing_env};
+
+    const STORAGE_FOR_CREATE_SERIES: Balance = 8540000000000000000000;
+    const STORAGE_FOR_MINT: Balance = 11280000000000000000000;
+    const STORAGE_APPROVE: Balance = 2610000000000000000000;
+
+    fn get_context(predecessor_account_id: ValidAccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .signer_account_id(predecessor_account_id.clone())
+            .predecessor_account_id(predecessor_account_id);
+        builder
+    }
+
+    /// (receiver_id, deposit) of every transfer receipt created in the current context
+    fn get_transfers() -> Vec<(AccountId, Balance)> {
+        get_created_receipts()
+            .iter()
+            .filter_map(|receipt| {
+                let receipt = serde_json::to_string(receipt).unwrap();
+                let receiver_id = receipt.split("\"receiver_id\":\"").nth(1)?.split('"').next()?;
+                let deposit = receipt.split("\"Transfer\":{\"deposit\":").nth(1)?.split('}').next()?;
+                Some((receiver_id.to_string(), deposit.parse().unwrap()))
+            })
+            .collect()
+    }
+
+    fn get_function_call_args(method_name: &str) -> Vec<serde_json::Value> {
+        get_created_receipts()
+            .iter()
+            .flat_map(|receipt| {
+                // u128 deposits only survive the round trip through a string
+                let receipt: serde_json::Value =
+                    serde_json::from_str(&serde_json::to_string(receipt).unwrap()).unwrap();
+                receipt["actions"].as_array().unwrap().clone()
+            })
+            .filter(|action| action["FunctionCall"]["method_name"] == method_name)
+            .map(|action| serde_json::from_str(action["FunctionCall"]["args"].as_str().unwrap()).unwrap())
+            .collect()
+    }
+
+    fn setup_contract() -> (VMContextBuilder, Contract) {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let contract = Contract::new_default_meta(accounts(0), accounts(4));
+        (context, contract)
+    }
+
+    #[test]
+    fn test_new() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new(
+            accounts(1),
+            accounts(4),
+            NFTContractMetadata {
+                spec: NFT_METADATA_SPEC.to_string(),
+                name: "Triple Triad".to_string(),
+                symbol: "TRIAD".to_string(),
+                icon: Some(DATA_IMAGE_SVG_APOLLO42_ICON.to_string()),
+                base_uri: Some("https://lh3.googleusercontent.com/".to_string()),
+                reference: None,
+                reference_hash: None,
+            },
+            500
+        );
+        testing_env!(context.is_view(true).build());
+        assert_eq!(contract.get_owner(), accounts(1).to_string());
+        assert_eq!(contract.nft_metadata().base_uri.unwrap(), "https://lh3.googleusercontent.com/".to_string());
+        assert_eq!(contract.nft_metadata().icon.unwrap(), DATA_IMAGE_SVG_APOLLO42_ICON.to_string());
+    }
+
+    fn sample_token_metadata(copies: Option<u64>) -> TokenMetadata {
+        TokenMetadata {
+            title: Some("Tsundere land".to_string()),
+            description: None,
+            media: Some(
+                "bafybeidzcan4nzcz7sczs4yzyxly4galgygnbjewipj6haco4kffoqpkiy".to_string()
+            ),
+            media_hash: None,
+            copies,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: None,
+            reference: Some(
+                "bafybeicg4ss7qh5odijfn2eogizuxkrdh3zlv4eftcmgnljwu7dm64uwji".to_string()
+            ),
+            reference_hash: None,
+        }
+    }
+
+    fn create_series(
+        contract: &mut Contract,
+        royalty: &HashMap<AccountId, u32>,
+        price: Option<U128>,
+        copies: Option<u64>,
+    ) {
+        contract.nft_create_series(
+            None,
+            sample_token_metadata(copies),
+            price,
+            Some(royalty.clone()),
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn test_create_series() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+        create_series(
+            &mut contract,
+            &royalty,
+            Some(U128::from(1 * 10u128.pow(24))),
+            None
+        );
+
+        let nft_series_return = contract.nft_get_series_single("1".to_string());
+        assert_eq!(
+            nft_series_return.creator_id,
+            accounts(1).to_string()
+        );
+
+        assert_eq!(
+            nft_series_return.token_series_id,
+            "1",
+        );
+
+        assert_eq!(
+            nft_series_return.royalty,
+            royalty,
+        );
+
+        assert!(
+            nft_series_return.metadata.copies.is_none()
+        );
+
+        assert_eq!(
+            nft_series_return.metadata.title.unwrap(),
+            "Tsundere land".to_string()
+        );
+
+        assert_eq!(
+            nft_series_return.metadata.reference.unwrap(),
+            "bafybeicg4ss7qh5odijfn2eogizuxkrdh3zlv4eftcmgnljwu7dm64uwji".to_string()
+        );
+
+    }
+
+    fn create_series_with_hashes(
+        contract: &mut Contract,
+        media_hash: Option<Base64VecU8>,
+        reference_hash: Option<Base64VecU8>,
+    ) {
+        let mut token_metadata = sample_token_metadata(None);
+        token_metadata.media_hash = media_hash;
+        token_metadata.reference_hash = reference_hash;
+        contract.nft_create_series(None, token_metadata, None, None, None, None);
+    }
+
+    #[test]
+    fn test_create_series_unenforced_metadata_hashes() {
+        let (mut context, mut contract) = setup_contract();
+        assert!(!contract.get_enforce_metadata_hashes());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        create_series_with_hashes(&mut contract, None, None);
+        assert!(contract.nft_get_series_single("1".to_string()).metadata.media_hash.is_none());
+    }
+
+    #[test]
+    fn test_create_series_enforced_metadata_hashes() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_enforce_metadata_hashes(true);
+        assert!(contract.get_enforce_metadata_hashes());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        create_series_with_hashes(
+            &mut contract,
+            Some(Base64VecU8::from(vec![1u8; 32])),
+            Some(Base64VecU8::from(vec![2u8; 32])),
+        );
+        let series = contract.nft_get_series_single("1".to_string());
+        assert_eq!(series.metadata.media_hash.unwrap().0, vec![1u8; 32]);
+        assert_eq!(series.metadata.reference_hash.unwrap().0, vec![2u8; 32]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token_metadata.reference_hash is required")]
+    fn test_invalid_create_series_enforced_missing_hash() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_enforce_metadata_hashes(true);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        create_series_with_hashes(&mut contract, Some(Base64VecU8::from(vec![1u8; 32])), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token_metadata.media_hash has to be 32 bytes")]
+    fn test_invalid_create_series_enforced_short_hash() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_enforce_metadata_hashes(true);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        create_series_with_hashes(
+            &mut contract,
+            Some(Base64VecU8::from(vec![1u8; 16])),
+            Some(Base64VecU8::from(vec![2u8; 32])),
+        );
+    }
+
+    #[test]
+    fn test_royalty_recipients() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+        royalty.insert(accounts(3).to_string(), 500);
+        create_series(&mut contract, &royalty, None, None);
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+        create_series(&mut contract, &royalty, None, None);
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(3).to_string(), 200);
+        create_series(&mut contract, &royalty, None, None);
+
+        let mut series_royalty = contract.nft_royalty_for_recipient(accounts(3));
+        series_royalty.sort();
+        assert_eq!(series_royalty, vec![("1".to_string(), 500), ("3".to_string(), 200)]);
+
+        assert_eq!(
+            contract.nft_royalty_recipients(None, None),
+            vec![(accounts(1).to_string(), 2000), (accounts(3).to_string(), 700)]
+        );
+        // pages over series, not recipients
+        assert_eq!(
+            contract.nft_royalty_recipients(Some(U128::from(1)), Some(1)),
+            vec![(accounts(1).to_string(), 1000)]
+        );
+        assert_eq!(
+            contract.nft_royalty_recipients(Some(U128::from(1)), Some(2)),
+            vec![(accounts(1).to_string(), 1000), (accounts(3).to_string(), 200)]
+        );
+    }
+
+    #[test]
+    fn test_create_series_idempotency_key() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let created = contract.nft_create_series(
+            None,
+            TokenMetadata {
+                title: Some("Tsundere land".to_string()),
+                description: None,
+                media: None,
+                media_hash: None,
+                copies: None,
+                issued_at: None,
+                expires_at: None,
+                starts_at: None,
+                updated_at: None,
+                extra: None,
+                reference: None,
+                reference_hash: None,
+            },
+            None,
+            None,
+            Some("retry-key".to_string()),
+            None,
+        );
+
+        let retried = contract.nft_create_series(None, created.metadata, None, None, Some("retry-key".to_string()), None);
+
+        assert_eq!(created.token_series_id, "1");
+        assert_eq!(retried.token_series_id, "1");
+        assert_eq!(contract.token_series_by_id.len(), 1);
+    }
+
+    #[test]
+    fn test_trusted_contract_lock() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.add_trusted_contract(accounts(3));
+        assert!(contract.is_trusted(accounts(3)));
+        assert!(!contract.is_trusted(accounts(2)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_APPROVE)
+            .build()
+        );
+        contract.nft_approve(token_id.clone(), accounts(3), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(STORAGE_APPROVE)
+            .build()
+        );
+        contract.nft_lock(token_id.clone());
+        assert_eq!(contract.nft_locker(token_id.clone()), Some(accounts(3).to_string()));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_unlock(token_id.clone());
+        assert_eq!(contract.nft_locker(token_id), None);
+    }
+
+    #[test]
+    fn test_unlock_after_trust_removed() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.add_trusted_contract(accounts(3));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_APPROVE)
+            .build()
+        );
+        contract.nft_approve(token_id.clone(), accounts(3), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(STORAGE_APPROVE)
+            .build()
+        );
+        contract.nft_lock(token_id.clone());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.remove_trusted_contract(accounts(3));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_unlock(token_id.clone());
+        assert_eq!(contract.nft_locker(token_id), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Trusted contract only")]
+    fn test_invalid_lock_untrusted() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(STORAGE_APPROVE)
+            .build()
+        );
+        contract.nft_lock(token_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is locked")]
+    fn test_invalid_transfer_locked_token() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.add_trusted_contract(accounts(3));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_APPROVE)
+            .build()
+        );
+        contract.nft_approve(token_id.clone(), accounts(3), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(STORAGE_APPROVE)
+            .build()
+        );
+        contract.nft_lock(token_id.clone());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(1), token_id, None, None);
+    }
+
+    #[test]
+    fn test_create_series_metadata_limits() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_metadata_limits(MetadataLimits {
+            extra: Some(16),
+            ..MetadataLimits::default()
+        });
+        assert_eq!(contract.get_metadata_limits().extra, Some(16));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut token_metadata = sample_token_metadata(None);
+        token_metadata.extra = Some("a".repeat(16));
+        let series = contract.nft_create_series(None, token_metadata, None, None, None, None);
+        assert_eq!(series.metadata.extra, Some("a".repeat(16)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Field extra exceeds max size")]
+    fn test_invalid_create_series_metadata_over_limit() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_metadata_limits(MetadataLimits {
+            extra: Some(16),
+            ..MetadataLimits::default()
+        });
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut token_metadata = sample_token_metadata(None);
+        token_metadata.extra = Some("a".repeat(17));
+        contract.nft_create_series(None, token_metadata, None, None, None, None);
+    }
+
+    #[test]
+    fn test_buy() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(
+            &mut contract,
+            &royalty,
+            Some(U128::from(1 * 10u128.pow(24))),
+            None
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1 * 10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+
+        let token_id = contract.nft_buy("1".to_string(), accounts(2), None, None);
+
+        let token_from_nft_token = contract.nft_token(token_id);
+        assert_eq!(
+            token_from_nft_token.unwrap().owner_id,
+            accounts(2).to_string()
+        )
+    }
+
+    #[test]
+    fn test_buy_refund_to() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(2 * 10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+
+        contract.nft_buy("1".to_string(), accounts(2), Some(accounts(3)), None);
+
+        let transfers = get_transfers();
+        let refund = transfers.iter().find(|(receiver_id, _)| *receiver_id == accounts(3).to_string());
+        assert!(refund.unwrap().1 >= 10u128.pow(24));
+        assert!(!transfers.iter().any(|(receiver_id, _)| *receiver_id == accounts(2).to_string()));
+    }
+
+    #[test]
+    fn test_buy_primary_split() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        let price = 10u128.pow(24);
+        create_series(&mut contract, &royalty, Some(U128::from(price)), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+
+        contract.nft_set_series_primary_split(
+            "1".to_string(),
+            Some(vec![(accounts(1).to_string(), 7000), (accounts(3).to_string(), 3000)])
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(price + STORAGE_FOR_MINT)
+            .build()
+        );
+
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+
+        let price_deducted = price - price * 300 / 10_000;
+        let transfers = get_transfers();
+        assert!(transfers.contains(&(accounts(1).to_string(), price_deducted * 7000 / 10_000)));
+        assert!(transfers.contains(&(accounts(3).to_string(), price_deducted * 3000 / 10_000)));
+        assert!(transfers.contains(&(accounts(4).to_string(), price * 300 / 10_000)));
+    }
+
+    #[test]
+    fn test_buy_primary_royalty() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        let price = 10u128.pow(24);
+        create_series(&mut contract, &royalty, Some(U128::from(price)), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+
+        let mut primary_royalty: HashMap<AccountId, u32> = HashMap::new();
+        primary_royalty.insert(accounts(3).to_string(), 2000);
+        contract.nft_set_series_primary_royalty("1".to_string(), Some(primary_royalty));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(price + STORAGE_FOR_MINT)
+            .build()
+        );
+
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+
+        let price_deducted = price - price * 300 / 10_000;
+        let transfers = get_transfers();
+        assert!(transfers.contains(&(accounts(3).to_string(), price_deducted * 2000 / 10_000)));
+        assert!(transfers.contains(&(accounts(1).to_string(), price_deducted - price_deducted * 2000 / 10_000)));
+        assert!(transfers.contains(&(accounts(4).to_string(), price * 300 / 10_000)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Primary split must sum to 10_000")]
+    fn test_invalid_primary_split_sum() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+
+        contract.nft_set_series_primary_split(
+            "1".to_string(),
+            Some(vec![(accounts(1).to_string(), 7000), (accounts(3).to_string(), 2000)])
+        );
+    }
+
+    #[test]
+    fn test_mint() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        let token_from_nft_token = contract.nft_token(token_id);
+        assert_eq!(
+            token_from_nft_token.unwrap().owner_id,
+            accounts(2).to_string()
+        )
+    }
+
+    #[test]
+    fn test_next_token_id() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, Some(2));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+
+        let next_token_id = contract.nft_next_token_id("1".to_string());
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        assert_eq!(next_token_id, Some(token_id));
+
+        let next_token_id = contract.nft_next_token_id("1".to_string());
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        assert_eq!(next_token_id, Some(token_id));
+
+        assert!(contract.nft_next_token_id("1".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_mint_auto_approve_market() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_auto_approve_market(Some(accounts(3)));
+        assert_eq!(contract.get_auto_approve_market(), Some(accounts(3).to_string()));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT + STORAGE_APPROVE)
+            .build()
+        );
+
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        let approved_account_ids = contract.nft_token(token_id).unwrap().approved_account_ids.unwrap();
+        assert_eq!(approved_account_ids.get(&accounts(3).to_string()), Some(&1u64));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_auto_approve_market(None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        assert!(contract.nft_token(token_id).unwrap().approved_account_ids.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mint_auto_approve_series_opt_out() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_auto_approve_market(Some(accounts(3)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_auto_approve("1".to_string(), false);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        assert!(contract.nft_token(token_id).unwrap().approved_account_ids.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_series_holders() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+
+        contract.nft_mint("1".to_string(), accounts(2), None);
+        contract.nft_mint("1".to_string(), accounts(3), None);
+        contract.nft_mint("1".to_string(), accounts(2), None);
+
+        let holders = contract.nft_series_holders("1".to_string(), None, None);
+        assert_eq!(holders.len(), 3);
+        for (token_id, owner_id) in holders.iter() {
+            assert_eq!(Some(owner_id.clone()), contract.tokens.owner_by_id.get(token_id));
+        }
+        assert_eq!(holders[1], ("1:2".to_string(), accounts(3).to_string()));
+
+        let holders = contract.nft_series_holders("1".to_string(), Some(U128::from(1)), Some(1));
+        assert_eq!(holders, vec![("1:2".to_string(), accounts(3).to_string())]);
+
+        // a burned edition leaves a short page, the next one still starts at from_index + limit
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_burn("1:1".to_string());
+        let holders = contract.nft_series_holders("1".to_string(), None, Some(2));
+        assert_eq!(holders, vec![("1:2".to_string(), accounts(3).to_string())]);
+        let holders = contract.nft_series_holders("1".to_string(), Some(U128::from(2)), Some(2));
+        assert_eq!(holders, vec![("1:3".to_string(), accounts(2).to_string())]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token series is not mintable")]
+    fn test_invalid_mint_non_mintable() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_non_mintable("1".to_string());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+
+        contract.nft_mint("1".to_string(), accounts(2), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token series is not mintable")]
+    fn test_invalid_mint_above_copies() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, Some(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+
+        contract.nft_mint("1".to_string(), accounts(2), None);
+        contract.nft_mint("1".to_string(), accounts(2), None);
+    }
+
+    #[test]
+    fn test_decrease_copies() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, Some(5));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+
+        contract.nft_mint("1".to_string(), accounts(2), None);
+        contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+
+        contract.nft_decrease_series_copies("1".to_string(), U64::from(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot decrease supply, already minted: 2")]
+    fn test_invalid_decrease_copies() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, Some(5));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+
+        contract.nft_mint("1".to_string(), accounts(2), None);
+        contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+
+        contract.nft_decrease_series_copies("1".to_string(), U64::from(4));
+    }
+
+    #[test]
+    fn test_close_series_capped() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, Some(5));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+
+        contract.nft_mint("1".to_string(), accounts(2), None);
+        contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+
+        let copies = contract.nft_close_series("1".to_string());
+        assert_eq!(copies, Some(U64::from(2)));
+
+        let token_series = contract.token_series_by_id.get(&"1".to_string()).unwrap();
+        assert_eq!(token_series.metadata.copies, Some(2));
+        assert!(!token_series.is_mintable);
+    }
+
+    #[test]
+    fn test_close_series_uncapped() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+
+        contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+
+        let copies = contract.nft_close_series("1".to_string());
+        assert!(copies.is_none());
+
+        let token_series = contract.token_series_by_id.get(&"1".to_string()).unwrap();
+        assert!(token_series.metadata.copies.is_none());
+        assert!(!token_series.is_mintable);
+    }
+
+    #[test]
+    #[should_panic( expected = "Not for sale" )]
+    fn test_invalid_buy_price_null() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, Some(U128::from(1 * 10u128.pow(24))), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+
+        contract.nft_set_series_price("1".to_string(), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1 * 10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+
+        let token_id = contract.nft_buy("1".to_string(), accounts(2), None, None);
+
+        let token_from_nft_token = contract.nft_token(token_id);
+        assert_eq!(
+            token_from_nft_token.unwrap().owner_id,
+            accounts(2).to_string()
+        )
+    }
+
+    #[test]
+    fn test_buy_near_max_price() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        let price = MAX_PRICE - 1;
+        create_series(&mut contract, &royalty, Some(U128::from(price)), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .account_balance(2 * MAX_PRICE)
+            .attached_deposit(price + STORAGE_FOR_MINT)
+            .build()
+        );
+
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+
+        let for_treasury = price * 300 / 10_000;
+        let transfers = get_transfers();
+        assert!(transfers.contains(&(accounts(1).to_string(), price - for_treasury)));
+        assert!(transfers.contains(&(accounts(4).to_string(), for_treasury)));
+    }
+
+    // xorshift64, keeps the property tests deterministic without extra deps
+    fn next_random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    fn test_compute_payout_invariants() {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let owner_id = "owner.near".to_string();
+
+        for _ in 0..2_000 {
+            let num_recipients = (next_random(&mut state) % 11) as usize;
+            let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+            let mut remaining_bps: u32 = 5000;
+            for index in 0..num_recipients {
+                let bps = (next_random(&mut state) % (remaining_bps as u64 + 1)) as u32;
+                remaining_bps -= bps;
+                royalty.insert(format!("recipient{}.near", index), bps);
+            }
+            // the owner may also be a royalty recipient
+            if next_random(&mut state) & 3 == 0 {
+                royalty.insert(owner_id.clone(), remaining_bps);
+            }
+
+            let magnitude = (next_random(&mut state) % 34) as u32;
+            let balance: Balance = (next_random(&mut state) as u128) % 10u128.pow(magnitude).max(1)
+                + 10u128.pow(magnitude);
+            let rounding = match next_random(&mut state) % 3 {
+                0 => RoundingMode::Floor,
+                1 => RoundingMode::Round,
+                _ => RoundingMode::Ceil,
+            };
+
+            let payout = compute_payout(&royalty, &owner_id, balance, rounding).payout;
+
+            let total: Balance = payout.values().map(|amount| amount.0).sum();
+            assert_eq!(total, balance);
+            for (account_id, amount) in payout.iter() {
+                if *account_id == owner_id {
+                    continue;
+                }
+                let bps = royalty[account_id] as u128;
+                let share = bps * balance;
+                match rounding {
+                    RoundingMode::Floor => assert!(amount.0 * 10_000 <= share),
+                    _ => assert!(amount.0 * 10_000 <= share + 9_999),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_royalty_to_payout_high_bps() {
+        assert_eq!(royalty_to_payout(5000, MAX_PRICE, RoundingMode::Floor).0, MAX_PRICE / 2);
+        assert_eq!(royalty_to_payout(10_000, u128::MAX / 10_000, RoundingMode::Floor).0, u128::MAX / 10_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Royalty payout overflow")]
+    fn test_invalid_royalty_to_payout_overflow() {
+        royalty_to_payout(10_000, u128::MAX, RoundingMode::Floor);
+    }
+
+    #[test]
+    #[should_panic( expected = "Price is higher than 1000000000000000000000000000000000" )]
+    fn test_invalid_price_shouldnt_be_higher_than_max_price() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, Some(U128::from(1_000_000_000 * 10u128.pow(24))), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+    }
+
+    #[test]
+    fn test_nft_burn() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+
+        contract.nft_burn(token_id.clone());
+        let token = contract.nft_token(token_id);
+        assert!(token.is_none());
+    }
+
+    #[test]
+    fn test_royalty_bps() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+        royalty.insert(accounts(3).to_string(), 500);
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        assert_eq!(contract.royalty_bps(token_id), 1500);
+    }
+
+    #[test]
+    fn test_nft_mint_charge_receiver_storage() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_charge_receiver_storage("1".to_string(), true);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT * 2)
+            .build()
+        );
+        let deposited = contract.storage_deposit(None).total.0;
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        assert_eq!(contract.nft_token(token_id).unwrap().owner_id, accounts(2).to_string());
+        let remaining = contract.storage_balance_of(accounts(2)).unwrap().total.0;
+        assert!(remaining < deposited);
+        assert!(deposited - remaining <= STORAGE_FOR_MINT);
+    }
+
+    #[test]
+    fn test_storage_deposit_and_withdraw_events() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let first_total = contract.storage_deposit(None).total.0;
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"{{"type":"storage_deposit","params":{{"account_id":"{}","amount":"{}","total":"{}"}}}}"#,
+                accounts(2), first_total, first_total
+            )]
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let total = contract.storage_deposit(Some(accounts(2))).total.0;
+        assert_eq!(total, first_total + STORAGE_FOR_MINT);
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"{{"type":"storage_deposit","params":{{"account_id":"{}","amount":"{}","total":"{}"}}}}"#,
+                accounts(2), STORAGE_FOR_MINT, total
+            )]
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.storage_withdraw(Some(U128(1000)));
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"{{"type":"storage_withdraw","params":{{"account_id":"{}","amount":"1000","total":"{}"}}}}"#,
+                accounts(2), total - 1000
+            )]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Must attach")]
+    fn test_invalid_nft_mint_charge_receiver_storage_without_balance() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_charge_receiver_storage("1".to_string(), true);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+    }
+
+    #[test]
+    fn test_nft_series_for_owner() {
+        let (mut context, mut contract) = setup_contract();
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        for _ in 0..3 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+                .build()
+            );
+            create_series(&mut contract, &royalty, None, None);
+        }
+
+        for (token_series_id, receiver_id) in [
+            ("3", accounts(2)),
+            ("1", accounts(2)),
+            ("2", accounts(3)),
+            ("1", accounts(2)),
+        ].iter() {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            contract.nft_mint(token_series_id.to_string(), receiver_id.clone(), None);
+        }
+
+        assert_eq!(
+            contract.nft_series_for_owner(accounts(2), None, None),
+            vec!["1".to_string(), "3".to_string()]
+        );
+        assert_eq!(
+            contract.nft_series_for_owner(accounts(2), Some(U128(1)), Some(1)),
+            vec!["3".to_string()]
+        );
+        assert!(contract.nft_series_for_owner(accounts(4), None, None).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Token series is not mintable")]
+    fn test_max_copies_per_series_caps_open_edition() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_max_copies_per_series(Some(2));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+        assert_eq!(contract.nft_get_series_single("1".to_string()).metadata.copies, Some(2));
+
+        for _ in 0..3 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            contract.nft_mint("1".to_string(), accounts(2), None);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Copies exceed max_copies_per_series")]
+    fn test_invalid_nft_create_series_copies_above_max() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_max_copies_per_series(Some(2));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, Some(3));
+    }
+
+    #[test]
+    fn test_nft_list_on_market() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_list_on_market(accounts(3), token_id.clone(), None, ListingArgs {
+            price: U128(10u128.pow(24)),
+            ft_token_id: "near".to_string(),
+            expiry: Some(U64(1_000)),
+        });
+
+        let args = get_function_call_args("nft_on_transfer");
+        assert_eq!(args.len(), 1);
+        let listing: ListingArgs = serde_json::from_str(args[0]["msg"].as_str().unwrap()).unwrap();
+        assert_eq!(listing, ListingArgs {
+            price: U128(10u128.pow(24)),
+            ft_token_id: "near".to_string(),
+            expiry: Some(U64(1_000)),
+        });
+        assert_eq!(contract.nft_token(token_id).unwrap().owner_id, accounts(3).to_string());
+    }
+
+    #[test]
+    fn test_nft_series_sale_status() {
+        let (mut context, mut contract) = setup_contract();
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        create_series(&mut contract, &royalty, None, None);
+        let status = contract.nft_series_sale_status("1".to_string());
+        assert!(!status.available);
+        assert_eq!(status.reason, Some("not for sale".to_string()));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), None);
+        let status = contract.nft_series_sale_status("2".to_string());
+        assert!(status.available);
+        assert_eq!(status.reason, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_non_mintable("2".to_string());
+        let status = contract.nft_series_sale_status("2".to_string());
+        assert!(!status.available);
+        assert_eq!(status.reason, Some("paused".to_string()));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), Some(1));
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("3".to_string(), accounts(2), None);
+        let status = contract.nft_series_sale_status("3".to_string());
+        assert!(!status.available);
+        assert_eq!(status.reason, Some("sold out".to_string()));
+        assert_eq!(status.price, Some(U128::from(10u128.pow(24))));
+    }
+
+    #[test]
+    fn test_nft_batch_mint_sold_out_event() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, Some(3));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT * 5)
+            .build()
+        );
+        let minted = contract.nft_batch_mint(
+            "1".to_string(),
+            vec![accounts(2), accounts(3), accounts(4), accounts(5), accounts(2)],
+        );
+        assert_eq!(minted, 3);
+        assert_eq!(contract.nft_token("1:3".to_string()).unwrap().owner_id, accounts(4).to_string());
+        assert!(contract.nft_token("1:4".to_string()).is_none());
+
+        let sold_out_logs: Vec<serde_json::Value> = get_logs()
+            .iter()
+            .filter_map(|log| serde_json::from_str::<serde_json::Value>(log).ok())
+            .filter(|log| log["type"] == "nft_series_sold_out")
+            .collect();
+        assert_eq!(sold_out_logs.len(), 1);
+        assert_eq!(sold_out_logs[0]["params"]["final_supply"], "3");
+        assert_eq!(sold_out_logs[0]["params"]["total_volume"], "0");
+    }
+
+    #[test]
+    fn test_nft_mint_edition() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, Some(3));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        assert_eq!(contract.nft_mint_edition("1".to_string(), 3, accounts(2)), "1:3");
+
+        for _ in 0..2 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            contract.nft_mint("1".to_string(), accounts(3), None);
+        }
+
+        assert_eq!(contract.nft_token("1:1".to_string()).unwrap().owner_id, accounts(3).to_string());
+        assert_eq!(contract.nft_token("1:2".to_string()).unwrap().owner_id, accounts(3).to_string());
+        assert_eq!(contract.nft_token("1:3".to_string()).unwrap().owner_id, accounts(2).to_string());
+        assert_eq!(contract.nft_next_token_id("1".to_string()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Edition already minted")]
+    fn test_invalid_nft_mint_edition_duplicate() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, Some(3));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint_edition("1".to_string(), 1, accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Edition out of range")]
+    fn test_invalid_nft_mint_edition_out_of_range() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, Some(3));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint_edition("1".to_string(), 4, accounts(2));
+    }
+
+    #[test]
+    fn test_nft_get_series_only_for_sale() {
+        let (mut context, mut contract) = setup_contract();
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        for copies in [None, None, Some(1)].iter() {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+                .build()
+            );
+            create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), *copies);
+        }
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_price("2".to_string(), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("3".to_string(), accounts(2), None);
+
+        let series = contract.nft_get_series(None, None, Some(true));
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].token_series_id, "1");
+        assert_eq!(contract.nft_get_series(None, None, None).len(), 3);
+    }
+
+    #[test]
+    fn test_nft_token_prepend_base_uri() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        let media = "bafybeidzcan4nzcz7sczs4yzyxly4galgygnbjewipj6haco4kffoqpkiy".to_string();
+        let metadata = contract.nft_token(token_id.clone()).unwrap().metadata.unwrap();
+        assert_eq!(metadata.media, Some(media.clone()));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_prepend_base_uri(true);
+
+        let metadata = contract.nft_token(token_id).unwrap().metadata.unwrap();
+        assert_eq!(metadata.media, Some(format!("https://lh3.googleusercontent.com/{}", media)));
+        assert_eq!(
+            with_base_uri("https://lh3.googleusercontent.com/", metadata.media.clone().unwrap()),
+            metadata.media.unwrap()
+        );
+        assert_eq!(with_base_uri("https://base/", "ipfs://cid".to_string()), "ipfs://cid");
+    }
+
+    #[test]
+    fn test_nft_reveal_series() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, Some(10));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_unrevealed("1".to_string());
+
+        let mut token_metadata = sample_token_metadata(None);
+        token_metadata.title = Some("Revealed".to_string());
+        token_metadata.media = Some("revealed-media".to_string());
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        contract.nft_set_revealed_metadata("1".to_string(), token_metadata);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        let metadata = contract.nft_token(token_id.clone()).unwrap().metadata.unwrap();
+        assert_eq!(metadata.title, Some("Tsundere land".to_string()));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_reveal_series("1".to_string());
+
+        let metadata = contract.nft_token(token_id).unwrap().metadata.unwrap();
+        assert_eq!(metadata.title, Some("Revealed".to_string()));
+        assert_eq!(metadata.media, Some("revealed-media".to_string()));
+        assert_eq!(metadata.copies, Some(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_invalid_nft_set_revealed_metadata_no_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_unrevealed("1".to_string());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build()
+        );
+        contract.nft_set_revealed_metadata("1".to_string(), sample_token_metadata(None));
+    }
+
+    #[test]
+    #[should_panic(expected = "Series already revealed")]
+    fn test_invalid_nft_set_revealed_metadata_revealed() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        contract.nft_set_revealed_metadata("1".to_string(), sample_token_metadata(None));
+    }
+
+    #[test]
+    fn test_nft_transfer_event() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(3), token_id.clone(), None, Some("gift".to_string()));
+
+        assert_eq!(contract.nft_token(token_id.clone()).unwrap().owner_id, accounts(3).to_string());
+        assert_eq!(
+            get_logs().last().unwrap(),
+            &format!(
+                r#"EVENT_JSON:{{"standard":"nep171","version":"1.0.0","event":"nft_transfer","data":[{{"old_owner_id":"{}","new_owner_id":"{}","token_ids":["{}"],"memo":"gift"}}]}}"#,
+                accounts(2), accounts(3), token_id
+            )
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(STORAGE_APPROVE)
+            .build()
+        );
+        contract.nft_approve(token_id.clone(), accounts(4), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(2), token_id.clone(), Some(1), None);
+
+        assert_eq!(contract.nft_token(token_id.clone()).unwrap().owner_id, accounts(2).to_string());
+        assert_eq!(
+            get_logs().last().unwrap(),
+            &format!(
+                r#"EVENT_JSON:{{"standard":"nep171","version":"1.0.0","event":"nft_transfer","data":[{{"authorized_id":"{}","old_owner_id":"{}","new_owner_id":"{}","token_ids":["{}"]}}]}}"#,
+                accounts(4), accounts(3), accounts(2), token_id
+            )
+        );
+    }
+
+    #[test]
+    fn test_nft_request_and_cancel_non_mintable() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        let start_time: Timestamp = 1618109122863866400;
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(start_time)
+            .attached_deposit(1)
+            .build()
+        );
+        let non_mintable_at = contract.nft_request_non_mintable("1".to_string());
+        assert_eq!(non_mintable_at, to_sec(start_time) + DEFAULT_NON_MINTABLE_GRACE_PERIOD);
+
+        // minting continues during the grace period
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_cancel_non_mintable("1".to_string());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(start_time + (DEFAULT_NON_MINTABLE_GRACE_PERIOD as u64 + 1) * 1_000_000_000)
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+        assert_eq!(contract.nft_next_token_id("1".to_string()), Some("1:3".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token series is not mintable")]
+    fn test_nft_request_non_mintable_after_grace_period() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        let start_time: Timestamp = 1618109122863866400;
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(start_time)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_request_non_mintable("1".to_string());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(start_time + (DEFAULT_NON_MINTABLE_GRACE_PERIOD as u64 + 1) * 1_000_000_000)
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        assert_eq!(contract.nft_next_token_id("1".to_string()), None);
+        assert_eq!(
+            contract.nft_series_sale_status("1".to_string()).reason,
+            Some("paused".to_string())
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+    }
+
+    #[test]
+    fn test_nft_tokens_minted_between() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        for block_timestamp in [100u64, 200, 300, 400] {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .block_timestamp(block_timestamp)
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            contract.nft_mint("1".to_string(), accounts(2), None);
+        }
+
+        let token_ids: Vec<TokenId> = contract
+            .nft_tokens_minted_between(U64(200), U64(400), None, None)
+            .into_iter()
+            .map(|token| token.token_id)
+            .collect();
+        assert_eq!(token_ids, vec!["1:2".to_string(), "1:3".to_string()]);
+
+        // limit bounds the scan, not the number of matches
+        let tokens = contract.nft_tokens_minted_between(U64(200), U64(400), None, Some(2));
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token_id, "1:2");
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many approvals")]
+    fn test_invalid_nft_approve_too_many_approvals() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_max_approvals_per_token(2);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_APPROVE)
+            .build()
+        );
+        contract.nft_approve(token_id.clone(), accounts(3), None);
+        contract.nft_approve(token_id.clone(), accounts(4), None);
+        // re-approving an approved account stays within the limit
+        contract.nft_approve(token_id.clone(), accounts(4), None);
+
+        contract.nft_approve(token_id, accounts(5), None);
+    }
+
+    #[test]
+    fn test_nft_transfer_call_min_gas() {
+        let (_, contract) = setup_contract();
+        assert_eq!(contract.nft_transfer_call_min_gas(), U64(40_000_000_000_000));
+    }
+
+    #[test]
+    fn test_nft_batch_approve() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        let mut token_ids = vec![];
+        for _ in 0..3 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            token_ids.push(contract.nft_mint("1".to_string(), accounts(2), None));
+        }
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_APPROVE * 3)
+            .build()
+        );
+        // approval ids are per token
+        let approval_ids = contract.nft_batch_approve(token_ids.clone(), accounts(3), None);
+        assert_eq!(approval_ids, vec![U64(1), U64(1), U64(1)]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        let approval_ids = contract.nft_batch_approve(
+            token_ids.clone(),
+            accounts(3),
+            Some("{}".to_string()),
+        );
+        assert_eq!(approval_ids, vec![U64(2), U64(2), U64(2)]);
+        assert_eq!(get_function_call_args("nft_on_approve").len(), 3);
+
+        for token_id in token_ids {
+            assert_eq!(
+                contract.nft_token(token_id).unwrap().approved_account_ids.unwrap().get(&accounts(3).to_string()),
+                Some(&2)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough gas")]
+    fn test_invalid_nft_batch_approve_not_enough_gas() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_APPROVE)
+            .prepaid_gas(GAS_FOR_NFT_APPROVE)
+            .build()
+        );
+        contract.nft_batch_approve(vec![token_id], accounts(3), Some("{}".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Predecessor must be token owner.")]
+    fn test_invalid_nft_batch_approve_not_owner() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_a = contract.nft_mint("1".to_string(), accounts(2), None);
+        let token_b = contract.nft_mint("1".to_string(), accounts(4), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_APPROVE * 2)
+            .build()
+        );
+        contract.nft_batch_approve(vec![token_a, token_b], accounts(3), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Royalty account must be a named account")]
+    fn test_invalid_create_series_implicit_royalty_account() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_strict_royalty_accounts(true);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(2).to_string(), 500);
+        create_series(&mut contract, &royalty, None, None);
+
+        royalty.clear();
+        royalty.insert("a".repeat(64), 500);
+        create_series(&mut contract, &royalty, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Royalty must include creator")]
+    fn test_invalid_create_series_without_creator_royalty() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_require_creator_royalty(true);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 500);
+        create_series(&mut contract, &royalty, None, None);
+
+        royalty.clear();
+        royalty.insert(accounts(2).to_string(), 500);
+        create_series(&mut contract, &royalty, None, None);
+    }
+
+    #[test]
+    fn test_repair_tokens_per_owner() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        for receiver_id in [accounts(2), accounts(3), accounts(2), accounts(2)] {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            contract.nft_mint("1".to_string(), receiver_id, None);
+        }
+
+        // drop a held token and add one the account doesn't hold
+        let tokens_per_owner = contract.tokens.tokens_per_owner.as_mut().unwrap();
+        let mut token_ids = tokens_per_owner.get(&accounts(2).to_string()).unwrap();
+        token_ids.remove(&"1:1".to_string());
+        token_ids.insert(&"1:2".to_string());
+        token_ids.insert(&"9:9".to_string());
+        tokens_per_owner.insert(&accounts(2).to_string(), &token_ids);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        assert_eq!(contract.repair_tokens_per_owner(accounts(2), None, Some(2)), U64(1));
+        assert_eq!(contract.repair_tokens_per_owner(accounts(2), Some(U128(2)), None), U64(3));
+
+        let mut token_ids: Vec<TokenId> = contract
+            .nft_tokens_for_owner(accounts(2), None, None)
+            .into_iter()
+            .map(|token| token.token_id)
+            .collect();
+        token_ids.sort();
+        assert_eq!(token_ids, vec!["1:1".to_string(), "1:3".to_string(), "1:4".to_string()]);
+    }
+
+    #[test]
+    fn test_nft_buy_custom_mint_metadata() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_allow_custom_mint_metadata("1".to_string(), true);
+
+        let custom_metadata = TokenMetadata {
+            title: None,
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: Some("{\"engraving\":\"Alice\"}".to_string()),
+            reference: None,
+            reference_hash: None,
+        };
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_buy("1".to_string(), accounts(2), None, Some(custom_metadata));
+        let metadata = contract.nft_token(token_id).unwrap().metadata.unwrap();
+        assert_eq!(metadata.extra, Some("{\"engraving\":\"Alice\"}".to_string()));
+        assert_eq!(metadata.title, Some("Tsundere land".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only extra can be set in custom mint metadata")]
+    fn test_invalid_nft_buy_custom_mint_metadata_media() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_allow_custom_mint_metadata("1".to_string(), true);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, Some(sample_token_metadata(None)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Holder cap reached")]
+    fn test_max_distinct_owners() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_max_distinct_owners(Some(2));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        let mut token_ids = vec![];
+        for receiver_id in [accounts(2), accounts(3), accounts(2)] {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            token_ids.push(contract.nft_mint("1".to_string(), receiver_id, None));
+        }
+        assert_eq!(contract.get_distinct_owners(), U64(2));
+
+        // transfers among existing holders stay allowed
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(3), token_ids[0].clone(), None, None);
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(2), token_ids[1].clone(), None, None);
+        assert_eq!(contract.get_distinct_owners(), U64(2));
+
+        contract.nft_transfer(accounts(4), token_ids[0].clone(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_invalid_nft_series_contract_metadata_no_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build()
+        );
+        contract.nft_set_series_contract_metadata("1".to_string(), None);
+    }
+
+    #[test]
+    fn test_nft_series_contract_metadata() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+        assert_eq!(contract.nft_series_contract_metadata("1".to_string()), None);
+
+        let series_contract_metadata = SeriesMeta {
+            name: "Tsundere Land".to_string(),
+            symbol: "TSUNDERE".to_string(),
+            banner: Some("banner".to_string()),
+            description: None,
+        };
+        contract.nft_set_series_contract_metadata("1".to_string(), Some(series_contract_metadata.clone()));
+        assert_eq!(
+            contract.nft_series_contract_metadata("1".to_string()),
+            Some(series_contract_metadata)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_invalid_nft_set_series_price_currency_no_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build()
+        );
+        contract.nft_set_series_price_currency("1".to_string(), None);
+    }
+
+    #[test]
+    fn test_nft_series_price_currency() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128(5_000_000)), None);
+        assert_eq!(contract.nft_series_price_currency("1".to_string()), None);
+
+        contract.nft_set_series_price_currency("1".to_string(), Some(CurrencyInfo {
+            contract: accounts(5).to_string(),
+            symbol: "USDC".to_string(),
+            decimals: 6,
+        }));
+
+        let price_currency = contract.nft_series_price_currency("1".to_string()).unwrap();
+        assert_eq!(price_currency.symbol, "USDC");
+        assert_eq!(price_currency.decimals, 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "Series is priced in a fungible token")]
+    fn test_invalid_nft_buy_price_currency() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128(5_000_000)), None);
+        contract.nft_set_series_price_currency("1".to_string(), Some(CurrencyInfo {
+            contract: accounts(5).to_string(),
+            symbol: "USDC".to_string(),
+            decimals: 6,
+        }));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(5_000_000 + STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_invalid_nft_set_series_icon_no_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build()
+        );
+        contract.nft_set_series_icon("1".to_string(), None);
+    }
+
+    #[test]
+    fn test_nft_series_icon() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+        create_series(&mut contract, &royalty, None, None);
+
+        let icon = "data:image/svg+xml,<svg></svg>".to_string();
+        contract.nft_set_series_icon("1".to_string(), Some(icon.clone()));
+
+        assert_eq!(contract.nft_series_icon("1".to_string()), Some(icon));
+        assert_eq!(
+            contract.nft_series_icon("2".to_string()),
+            Some(DATA_IMAGE_SVG_APOLLO42_ICON.to_string())
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        let contract_icon = "data:image/png;base64,AAAA".to_string();
+        contract.set_contract_icon(Some(contract_icon.clone()));
+        assert_eq!(contract.nft_series_icon("2".to_string()), Some(contract_icon));
+    }
+
+    #[test]
+    #[should_panic(expected = "Icon exceeds 16384 bytes")]
+    fn test_invalid_nft_set_series_icon_too_large() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        contract.nft_set_series_icon("1".to_string(), Some("a".repeat(MAX_SERIES_ICON_LEN + 1)));
+    }
+
+    #[test]
+    fn test_nft_resolve_transfer_burn_on_reject() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_burn_on_transfer_reject("2".to_string(), true);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let returned_token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        let burned_token_id = contract.nft_mint("2".to_string(), accounts(2), None);
+        let kept_token_id = contract.nft_mint("2".to_string(), accounts(2), None);
+
+        for token_id in [returned_token_id.clone(), burned_token_id.clone()] {
+            testing_env!(context
+                .predecessor_account_id(accounts(2))
+                .attached_deposit(1)
+                .build()
+            );
+            contract.nft_transfer_call(accounts(3), token_id.clone(), None, None, "".to_string());
+
+            testing_env!(
+                context.predecessor_account_id(accounts(0)).attached_deposit(0).build(),
+                VMConfig::default(),
+                RuntimeFeesConfig::default(),
+                Default::default(),
+                vec![PromiseResult::Failed]
+            );
+            assert!(!contract.nft_resolve_transfer(
+                accounts(2).to_string(),
+                accounts(3).to_string(),
+                token_id,
+                None,
+            ));
+        }
+
+        assert_eq!(contract.nft_token(returned_token_id).unwrap().owner_id, accounts(2).to_string());
+        assert!(contract.nft_token(burned_token_id).is_none());
+        assert!(contract.nft_token(kept_token_id).is_some());
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(2), "1".to_string()), U64(1));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(2), "2".to_string()), U64(1));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(3), "1".to_string()), U64(0));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(3), "2".to_string()), U64(0));
+        assert_eq!(contract.nft_total_burned(), U128(1));
+    }
+
+    #[test]
+    fn test_nft_series_by_tag() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        for extra in [
+            Some("{\"category\":\"music\"}"),
+            Some("{\"category\":\"art\"}"),
+            Some("not json"),
+            None,
+            Some("{\"category\":\"music\",\"year\":2021}"),
+        ] {
+            let mut token_metadata = sample_token_metadata(None);
+            token_metadata.extra = extra.map(|extra| extra.to_string());
+            contract.nft_create_series(None, token_metadata, None, None, None, None);
+        }
+
+        let token_series_ids: Vec<TokenSeriesId> = contract
+            .nft_series_by_tag("category".to_string(), "music".to_string(), None, None)
+            .into_iter()
+            .map(|token_series| token_series.token_series_id)
+            .collect();
+        assert_eq!(token_series_ids, vec!["1".to_string(), "5".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot transfer to self")]
+    fn test_invalid_nft_transfer_to_self() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(2), token_id, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Series sale limit reached")]
+    fn test_nft_buy_max_sellable() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), Some(4));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_max_sellable("1".to_string(), Some(2));
+
+        for _ in 0..2 {
+            testing_env!(context
+                .predecessor_account_id(accounts(2))
+                .attached_deposit(10u128.pow(24) + STORAGE_FOR_MINT)
+                .build()
+            );
+            contract.nft_buy("1".to_string(), accounts(2), None, None);
+        }
+        assert_eq!(
+            contract.nft_series_sale_status("1".to_string()).reason,
+            Some("sale limit reached".to_string())
+        );
+
+        // the creator can still mint up to copies
+        for _ in 0..2 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            contract.nft_mint("1".to_string(), accounts(3), None);
+        }
+        assert_eq!(contract.nft_supply_for_series("1".to_string()), U64(4));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+    }
+
+    #[test]
+    fn test_nft_series_fee_snapshot() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        assert_eq!(contract.nft_series_fee_snapshot("1".to_string()), Some(300));
+        assert_eq!(contract.nft_series_fee_snapshot("2".to_string()), None);
+    }
+
+    #[test]
+    fn test_nft_transfer_payout_secondary_treasury_bps() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_secondary_treasury_bps(250);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        let balance = 10u128.pow(24);
+        let payout = contract.nft_transfer_payout(
+            accounts(3),
+            token_id,
+            None,
+            Some(U128(balance)),
+            Some(10)
+        ).unwrap();
+
+        let mut payout_calc: HashMap<AccountId, U128> = HashMap::new();
+        payout_calc.insert(accounts(1).to_string(), U128(balance * 1000 / 10_000));
+        payout_calc.insert(accounts(4).to_string(), U128(balance * 250 / 10_000));
+        payout_calc.insert(accounts(2).to_string(), U128(balance * 8750 / 10_000));
+        assert_eq!(payout.payout, payout_calc);
+    }
+
+    #[test]
+    fn test_nft_revert_mint() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, Some(10));
+
+        let mint_time: Timestamp = 1618109122863866400;
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(mint_time)
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+        let token_id = contract.nft_mint("1".to_string(), accounts(3), None);
+        assert_eq!(token_id, "1:2");
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(mint_time + REVERT_MINT_WINDOW)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_revert_mint(token_id.clone());
+
+        assert!(contract.nft_token(token_id).is_none());
+        assert!(!contract.nft_has_minted(accounts(3), "1".to_string()));
+        assert_eq!(contract.nft_total_minted(), U128(1));
+        assert_eq!(contract.nft_total_burned(), U128(0));
+        assert_eq!(contract.nft_supply_for_series("1".to_string()), U64(1));
+        assert_eq!(contract.nft_next_token_id("1".to_string()), Some("1:2".to_string()));
+    }
+
+    #[test]
+    fn test_nft_revert_mint_sold_out() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_max_distinct_owners(Some(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, Some(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        assert!(!contract.nft_get_series_single("1".to_string()).is_mintable);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_revert_mint(token_id);
+        assert!(contract.nft_get_series_single("1".to_string()).is_mintable);
+        assert_eq!(contract.get_distinct_owners(), U64(0));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        assert_eq!(contract.nft_mint("1".to_string(), accounts(3), None), "1:1");
+        assert_eq!(contract.nft_token("1:1".to_string()).unwrap().owner_id, accounts(3).to_string());
+        assert!(!contract.nft_get_series_single("1".to_string()).is_mintable);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only creator mints can be reverted")]
+    fn test_invalid_nft_revert_mint_bought() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_buy("1".to_string(), accounts(2), None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_revert_mint(token_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Revert window is over")]
+    fn test_invalid_nft_revert_mint_after_window() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, Some(10));
+
+        let mint_time: Timestamp = 1618109122863866400;
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(mint_time)
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(mint_time + REVERT_MINT_WINDOW + 1)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_revert_mint(token_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token already transferred")]
+    fn test_invalid_nft_revert_mint_transferred() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, Some(10));
+
+        let mint_time: Timestamp = 1618109122863866400;
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(mint_time)
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(mint_time)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(3), token_id.clone(), None, None);
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(mint_time)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(2), token_id.clone(), None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(mint_time)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_revert_mint(token_id);
+    }
+
+    #[test]
+    fn test_nft_ownership_changes_since() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_index(10)
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        let other_token_id = contract.nft_mint("1".to_string(), accounts(3), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_index(20)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(3), token_id.clone(), None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_index(30)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_burn(other_token_id.clone());
+
+        let changes = contract.nft_ownership_changes_since(U64(20), None, None);
+        assert_eq!(changes, vec![
+            ChangeRecord {
+                token_id: token_id.clone(),
+                from: Some(accounts(2).to_string()),
+                to: Some(accounts(3).to_string()),
+                block_height: U64(20),
+            },
+            ChangeRecord {
+                token_id: other_token_id,
+                from: Some(accounts(3).to_string()),
+                to: None,
+                block_height: U64(30),
+            },
+        ]);
+
+        let changes = contract.nft_ownership_changes_since(U64(0), None, Some(1));
+        assert_eq!(changes, vec![ChangeRecord {
+            token_id,
+            from: None,
+            to: Some(accounts(2).to_string()),
+            block_height: U64(10),
+        }]);
+        assert_eq!(contract.nft_ownership_changes_since(U64(31), None, None), vec![]);
+    }
+
+    #[test]
+    fn test_nft_batch_set_non_mintable() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+        create_series(&mut contract, &royalty, None, None);
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_batch_set_non_mintable(vec!["1".to_string(), "2".to_string(), "3".to_string()]);
+
+        for token_series_id in ["1", "2", "3"] {
+            assert!(!contract.token_series_by_id.get(&token_series_id.to_string()).unwrap().is_mintable);
+        }
+        assert_eq!(
+            get_logs().last().unwrap(),
+            &json!({
+                "type": "nft_batch_set_non_mintable",
+                "params": {
+                    "token_series_ids": ["1", "2", "3"],
+                }
+            }).to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Creator only")]
+    fn test_invalid_nft_batch_set_non_mintable_not_creator() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_batch_set_non_mintable(vec!["1".to_string(), "2".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot update more than 50 series in one batch")]
+    fn test_invalid_nft_batch_set_non_mintable_over_limit() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_batch_set_non_mintable(vec!["1".to_string(); MAX_BATCH_SET_NON_MINTABLE + 1]);
+    }
+
+    #[test]
+    fn test_nft_buy_change() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        let price = 10u128.pow(24);
+        create_series(&mut contract, &royalty, Some(U128::from(price)), None);
+
+        let attached = price + STORAGE_FOR_MINT;
+        for _ in 0..2 {
+            testing_env!(context
+                .predecessor_account_id(accounts(2))
+                .attached_deposit(attached)
+                .build()
+            );
+            contract.nft_buy("1".to_string(), accounts(2), None, None);
+        }
+
+        let change = contract.nft_buy_change("1".to_string(), U128(attached));
+        assert!(change.0 > 0);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(attached)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+        let refund: Balance = get_transfers()
+            .iter()
+            .filter(|(receiver_id, _)| receiver_id == accounts(2).as_ref())
+            .map(|(_, deposit)| deposit)
+            .sum();
+        assert_eq!(change.0, refund);
+
+        assert_eq!(contract.nft_buy_change("1".to_string(), U128(price)), U128(0));
+    }
+
+    fn setup_allowlisted_series() -> (VMContextBuilder, Contract) {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_set_series_allowlist("1".to_string(), vec![accounts(2), accounts(3)]);
+        (context, contract)
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_invalid_nft_set_series_allowlist_no_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build()
+        );
+        contract.nft_set_series_allowlist("1".to_string(), vec![accounts(2)]);
+    }
+
+    #[test]
+    fn test_nft_buy_allowlisted() {
+        let (mut context, mut contract) = setup_allowlisted_series();
+        assert!(contract.nft_is_allowlisted("1".to_string(), accounts(2)));
+        assert!(!contract.nft_is_allowlisted("1".to_string(), accounts(5)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .attached_deposit(10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_buy("1".to_string(), accounts(2), None, None);
+        assert_eq!(contract.nft_token(token_id).unwrap().owner_id, accounts(2).to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Receiver not allowlisted")]
+    fn test_invalid_nft_buy_not_allowlisted() {
+        let (mut context, mut contract) = setup_allowlisted_series();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .attached_deposit(10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(5), None, None);
+    }
+
+    #[test]
+    fn test_nft_clear_series_allowlist() {
+        let (mut context, mut contract) = setup_allowlisted_series();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_clear_series_allowlist("1".to_string());
+        assert!(contract.nft_is_allowlisted("1".to_string(), accounts(5)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .attached_deposit(10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_buy("1".to_string(), accounts(5), None, None);
+        assert_eq!(contract.nft_token(token_id).unwrap().owner_id, accounts(5).to_string());
+    }
+
+    #[test]
+    fn test_batch_limits() {
+        let (_, contract) = setup_contract();
+        assert_eq!(contract.batch_limits(), BatchLimits {
+            mint: MAX_BATCH_MINT as u32,
+            burn: MAX_BATCH_BURN as u32,
+            transfer: MAX_BATCH_TRANSFER as u32,
+            approve: MAX_BATCH_APPROVE as u32,
+            payout: MAX_BATCH_PAYOUT as u32,
+            set_non_mintable: MAX_BATCH_SET_NON_MINTABLE as u32,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot mint more than 50 tokens in one batch")]
+    fn test_invalid_nft_batch_mint_over_limit() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT * (MAX_BATCH_MINT as u128 + 1))
+            .build()
+        );
+        contract.nft_batch_mint("1".to_string(), vec![accounts(2); MAX_BATCH_MINT + 1]);
+    }
+
+    #[test]
+    fn test_nft_clone_series() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1_000);
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let clone = contract.nft_clone_series("1".to_string(), Some(CloneOverrides {
+            title: Some("Tsundere land v2".to_string()),
+            price: Some(U128::from(2 * 10u128.pow(24))),
+            ..Default::default()
+        }));
+
+        assert_eq!(clone.token_series_id, "2");
+        assert_eq!(clone.royalty, royalty);
+        assert_eq!(clone.metadata.title, Some("Tsundere land v2".to_string()));
+        assert_eq!(clone.metadata.media, sample_token_metadata(None).media);
+        assert_eq!(contract.nft_supply_for_series("2".to_string()), U64(0));
+        assert_eq!(contract.nft_get_series_price("2".to_string()), Some(U128::from(2 * 10u128.pow(24))));
+    }
+
+    #[test]
+    fn test_nft_clone_series_price_currency() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128(5_000_000)), None);
+        contract.nft_set_series_price_currency("1".to_string(), Some(CurrencyInfo {
+            contract: accounts(5).to_string(),
+            symbol: "USDC".to_string(),
+            decimals: 6,
+        }));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        contract.nft_clone_series("1".to_string(), None);
+        assert_eq!(contract.nft_series_price_currency("2".to_string()), None);
+        assert_eq!(contract.nft_get_series_price("2".to_string()), None);
+    }
+
+    #[test]
+    fn test_nft_buy_free_mint() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128(0)), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_buy("1".to_string(), accounts(2), None, None);
+
+        assert_eq!(contract.nft_token(token_id.clone()).unwrap().owner_id, accounts(2).to_string());
+        assert!(get_transfers().iter().all(|(receiver_id, _)| receiver_id == accounts(2).as_ref()));
+        let mint_log: serde_json::Value = serde_json::from_str(
+            get_logs().last().unwrap().trim_start_matches("EVENT_JSON:")
+        ).unwrap();
+        assert_eq!(mint_log["data"][0]["token_ids"][0], token_id);
+        assert_eq!(mint_log["data"][0]["memo"], json!({"price": "0"}).to_string());
+    }
+
+    #[test]
+    fn test_nft_series_by_popularity() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        for _ in 0..3 {
+            create_series(&mut contract, &royalty, None, None);
+        }
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT * 3)
+            .build()
+        );
+        for (token_series_id, supply) in [("1", 1), ("2", 0), ("3", 2)] {
+            for _ in 0..supply {
+                contract.nft_mint(token_series_id.to_string(), accounts(2), None);
+            }
+        }
+
+        let ranking: Vec<TokenSeriesId> = contract
+            .nft_series_by_popularity(None, None)
+            .into_iter()
+            .map(|token_series| token_series.token_series_id)
+            .collect();
+        assert_eq!(ranking, vec!["3", "1", "2"]);
+
+        let page = contract.nft_series_by_popularity(Some(U128(1)), Some(1));
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].token_series_id, "1");
+    }
+
+    #[test]
+    fn test_nft_payout_safe() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+        royalty.insert(accounts(3).to_string(), 500);
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        let result = contract.nft_payout_safe(token_id.clone(), U128(1000), 1);
+        assert!(!result.ok);
+        assert!(result.payout.is_none());
+        assert_eq!(result.error, Some("Market cannot payout to that many receivers".to_string()));
+
+        let result = contract.nft_payout_safe(token_id, U128(1000), 10);
+        assert!(result.ok);
+        assert_eq!(result.payout.unwrap().payout.get(&accounts(2).to_string()), Some(&U128(850)));
+
+        let result = contract.nft_payout_safe("1:9".to_string(), U128(1000), 10);
+        assert_eq!(result.error, Some("No token id".to_string()));
+    }
+
+    #[test]
+    fn test_nft_tokens_for_owner_sorted() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        for _ in 0..10 {
+            create_series(&mut contract, &royalty, None, None);
+        }
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT * 12)
+            .build()
+        );
+        contract.nft_mint("10".to_string(), accounts(2), None);
+        for _ in 0..10 {
+            contract.nft_mint("2".to_string(), accounts(2), None);
+        }
+        contract.nft_mint("9".to_string(), accounts(2), None);
+
+        let mut token_ids: Vec<TokenId> = contract
+            .nft_tokens_for_owner_sorted(accounts(2), None, Some(6))
+            .into_iter()
+            .map(|token| token.token_id)
+            .collect();
+        token_ids.extend(
+            contract
+                .nft_tokens_for_owner_sorted(accounts(2), Some(U128(6)), Some(6))
+                .into_iter()
+                .map(|token| token.token_id)
+        );
+
+        let mut expected: Vec<TokenId> = (1..=10).map(|edition| format!("2:{}", edition)).collect();
+        expected.push("9:1".to_string());
+        expected.push("10:1".to_string());
+        assert_eq!(token_ids, expected);
+    }
+
+    #[test]
+    fn test_nft_creator_stats() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        let price = 10u128.pow(24);
+        create_series(&mut contract, &royalty, Some(U128::from(price)), None);
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(price + STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+
+        assert_eq!(contract.nft_creator_stats(accounts(1)), CreatorStats {
+            series_count: U64(2),
+            total_minted: U64(1),
+            total_volume: U128(price),
+            pending_payout: U128(0),
+        });
+        assert_eq!(contract.nft_creator_stats(accounts(2)).series_count, U64(0));
+    }
+
+    fn setup_approved_tokens() -> (VMContextBuilder, Contract, Vec<TokenId>) {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        let mut token_ids = vec![];
+        for _ in 0..2 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+            testing_env!(context
+                .predecessor_account_id(accounts(2))
+                .attached_deposit(STORAGE_APPROVE)
+                .build()
+            );
+            contract.nft_approve(token_id.clone(), accounts(3), None);
+            token_ids.push(token_id);
+        }
+        (context, contract, token_ids)
+    }
+
+    #[test]
+    fn test_nft_transfer_clears_approvals() {
+        let (mut context, mut contract, token_ids) = setup_approved_tokens();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(5), token_ids[0].clone(), None, None);
+        contract.nft_transfer_unsafe(accounts(5), token_ids[1].clone(), None, None);
+
+        let approvals_by_id = contract.tokens.approvals_by_id.as_ref().unwrap();
+        for token_id in token_ids.iter() {
+            assert!(approvals_by_id.get(token_id).is_none());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized")]
+    fn test_invalid_nft_transfer_by_previous_owner_approval() {
+        let (mut context, mut contract, token_ids) = setup_approved_tokens();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer_unsafe(accounts(5), token_ids[0].clone(), None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(3), token_ids[0].clone(), Some(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Media type not allowed")]
+    fn test_invalid_create_series_media_type_not_allowed() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_allowed_media_types(vec!["image/png".to_string()]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let series = contract.nft_create_series(
+            None,
+            sample_token_metadata(None),
+            None,
+            None,
+            None,
+            Some("image/png".to_string()),
+        );
+        assert_eq!(contract.nft_series_media_type(series.token_series_id), Some("image/png".to_string()));
+
+        contract.nft_create_series(
+            None,
+            sample_token_metadata(None),
+            None,
+            None,
+            None,
+            Some("video/mp4".to_string()),
+        );
+    }
+
+    #[test]
+    fn test_nft_batch_mint_range() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        let receiver_ids: Vec<ValidAccountId> = (0..10).map(|index| accounts(index % 6)).collect();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT * 6)
+            .build()
+        );
+        assert_eq!(contract.nft_batch_mint_range("1".to_string(), receiver_ids.clone(), 0, 6), 6);
+        // a retry of the first range after it landed mints nothing
+        assert_eq!(contract.nft_batch_mint_range("1".to_string(), receiver_ids.clone(), 0, 6), 0);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT * 4)
+            .build()
+        );
+        // overlaps the first range, already minted receivers are skipped
+        assert_eq!(contract.nft_batch_mint_range("1".to_string(), receiver_ids.clone(), 4, 6), 4);
+
+        assert_eq!(contract.nft_batch_mint_cursor("1".to_string(), receiver_ids), 10);
+        assert_eq!(contract.nft_supply_for_series("1".to_string()), U64(10));
+        let owners: Vec<AccountId> = (1..=10)
+            .map(|edition| contract.nft_token(format!("1:{}", edition)).unwrap().owner_id)
+            .collect();
+        let expected: Vec<AccountId> = (0..10).map(|index| accounts(index % 6).to_string()).collect();
+        assert_eq!(owners, expected);
+    }
+
+    #[test]
+    fn test_nft_series_royalty_headroom() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 600);
+        royalty.insert(accounts(2).to_string(), 400);
+        create_series(&mut contract, &royalty, None, None);
+
+        assert_eq!(contract.nft_series_royalty_headroom("1".to_string()), 4000);
+    }
+
+    fn setup_mint_cooldown() -> (VMContextBuilder, Contract) {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_mint_cooldown_secs(Some(60));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(1_000 * 10u64.pow(9))
+            .attached_deposit(10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_nft_buy_after_mint_cooldown() {
+        let (mut context, mut contract) = setup_mint_cooldown();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(1_001 * 10u64.pow(9))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(1_060 * 10u64.pow(9))
+            .attached_deposit(10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_buy("1".to_string(), accounts(2), None, None);
+        assert_eq!(token_id, "1:3");
+    }
+
+    #[test]
+    #[should_panic(expected = "Mint cooldown active")]
+    fn test_invalid_nft_buy_during_mint_cooldown() {
+        let (mut context, mut contract) = setup_mint_cooldown();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(1_059 * 10u64.pow(9))
+            .attached_deposit(10u128.pow(24) + STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+    }
+
+    #[test]
+    fn test_nft_export_series() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), Some(10));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT * 2)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+        contract.nft_mint("1".to_string(), accounts(3), None);
+
+        let export = contract.nft_export_series("1".to_string());
+        let export: SeriesExport = serde_json::from_str(&serde_json::to_string(&export).unwrap()).unwrap();
+
+        assert_eq!(export.token_series_id, "1");
+        assert_eq!(export.creator_id, accounts(1).to_string());
+        assert_eq!(export.metadata, sample_token_metadata(Some(10)));
+        assert_eq!(export.royalty, royalty);
+        assert_eq!(export.price, Some(U128::from(10u128.pow(24))));
+        assert!(export.is_mintable);
+        assert_eq!(export.supply, U64(2));
+        assert_eq!(export.tokens, vec![
+            ("1:1".to_string(), accounts(2).to_string()),
+            ("1:2".to_string(), accounts(3).to_string()),
+        ]);
+        assert_eq!(export.next_index, None);
+    }
+
+    #[test]
+    fn test_admin_import_series() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), Some(10));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT * 2)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+        contract.nft_mint("1".to_string(), accounts(3), None);
+        let export = contract.nft_export_series("1".to_string());
+
+        // fresh contract storage
+        near_sdk::env::take_blockchain_interface();
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES + STORAGE_FOR_MINT * 2)
+            .build()
+        );
+        let series = contract.admin_import_series(export.clone());
+
+        assert_eq!(series.token_series_id, "1");
+        assert_eq!(series.creator_id, accounts(1).to_string());
+        assert_eq!(series.royalty, royalty);
+        assert_eq!(contract.nft_token("1:1".to_string()).unwrap().owner_id, accounts(2).to_string());
+        assert_eq!(contract.nft_token("1:2".to_string()).unwrap().owner_id, accounts(3).to_string());
+        assert_eq!(contract.nft_next_token_id("1".to_string()), Some("1:3".to_string()));
+        let reexport = contract.nft_export_series("1".to_string());
+        assert_eq!(reexport.tokens, export.tokens);
+        assert_eq!(reexport.price, export.price);
+    }
+
+    #[test]
+    fn test_admin_import_series_holders() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, Some(3));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT * 3)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+        contract.nft_mint("1".to_string(), accounts(3), None);
+        contract.nft_mint("1".to_string(), accounts(3), None);
+        let mut export = contract.nft_export_series("1".to_string());
+        let rest = export.tokens.split_off(1);
+
+        // fresh contract storage, a restore ignores the holder cap
+        near_sdk::env::take_blockchain_interface();
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_max_distinct_owners(Some(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES + STORAGE_FOR_MINT * 3)
+            .build()
+        );
+        contract.admin_import_series(export);
+        contract.admin_import_series_holders("1".to_string(), rest);
+
+        assert_eq!(contract.nft_token("1:3".to_string()).unwrap().owner_id, accounts(3).to_string());
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(3), "1".to_string()), U64(2));
+        assert_eq!(contract.get_distinct_owners(), U64(2));
+        assert_eq!(contract.nft_total_minted(), U128(3));
+        assert!(!contract.nft_has_minted(accounts(3), "1".to_string()));
+        assert!(!contract.nft_get_series_single("1".to_string()).is_mintable);
+        assert!(get_logs().iter().all(|log| !log.contains("nft_series_sold_out")));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token series already exists")]
+    fn test_invalid_admin_import_series_existing_id() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+        let export = contract.nft_export_series("1".to_string());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        contract.admin_import_series(export);
+    }
+
+    /// vesting start of the sale made by setup_vested_sale, the next day boundary
+    const VESTED_SALE_START: u64 = 11 * 86_400;
+
+    /// creator share of a 1 NEAR sale made at t = 10 days under a 100s cliff / 1000s vesting
+    fn setup_vested_sale() -> (VMContextBuilder, Contract, Balance) {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_vesting(Some(Vesting { cliff: 100, duration: 1000 }));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        let price = 10u128.pow(24);
+        create_series(&mut contract, &royalty, Some(U128::from(price)), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(10 * 86_400 * 10u64.pow(9))
+            // the buyer also pays for the storage of the vesting grant
+            .attached_deposit(price + 2 * STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+        assert!(get_transfers().iter().all(|(receiver_id, _)| receiver_id != accounts(1).as_ref()));
+
+        let for_treasury = price * contract.get_transaction_fee().current_fee as u128 / 10_000;
+        (context, contract, price - for_treasury)
+    }
+
+    #[test]
+    fn test_vested_available() {
+        let (mut context, contract, share) = setup_vested_sale();
+
+        for (elapsed, expected) in [(99, 0), (100, share / 10), (500, share / 2), (1000, share), (5000, share)] {
+            testing_env!(context
+                .block_timestamp((VESTED_SALE_START + elapsed) * 10u64.pow(9))
+                .build()
+            );
+            assert_eq!(contract.vested_available(accounts(1)), U128(expected));
+        }
+    }
+
+    #[test]
+    fn test_withdraw_vested() {
+        let (mut context, mut contract, share) = setup_vested_sale();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp((VESTED_SALE_START + 500) * 10u64.pow(9))
+            .attached_deposit(1)
+            .build()
+        );
+        assert_eq!(contract.withdraw_vested(), U128(share / 2));
+        assert_eq!(get_transfers(), vec![(accounts(1).to_string(), share / 2)]);
+        assert_eq!(contract.vested_available(accounts(1)), U128(0));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp((VESTED_SALE_START + 1_000) * 10u64.pow(9))
+            .attached_deposit(1)
+            .build()
+        );
+        assert_eq!(contract.withdraw_vested(), U128(share - share / 2));
+        assert!(contract.vesting_balances.get(&accounts(1).to_string()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Nothing vested yet")]
+    fn test_invalid_withdraw_vested_before_cliff() {
+        let (mut context, mut contract, _) = setup_vested_sale();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp((VESTED_SALE_START + 99) * 10u64.pow(9))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.withdraw_vested();
+    }
+
+    #[test]
+    fn test_vesting_grants_merge_per_day() {
+        let (mut context, mut contract, share) = setup_vested_sale();
+
+        for timestamp in [10 * 86_400 + 3_600, 11 * 86_400] {
+            testing_env!(context
+                .predecessor_account_id(accounts(2))
+                .block_timestamp(timestamp * 10u64.pow(9))
+                .attached_deposit(10u128.pow(24) + 2 * STORAGE_FOR_MINT)
+                .build()
+            );
+            contract.nft_buy("1".to_string(), accounts(2), None, None);
+        }
+
+        let grants = contract.vesting_balances.get(&accounts(1).to_string()).unwrap();
+        assert_eq!(grants.len(), 2);
+        assert_eq!(grants[0].start, VESTED_SALE_START as TimestampSec);
+        assert_eq!(grants[0].amount, 2 * share);
+        assert_eq!(grants[1].start, (VESTED_SALE_START + 86_400) as TimestampSec);
+        assert_eq!(grants[1].amount, share);
+    }
+
+    #[test]
+    fn test_nft_tokens_approved_for() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        let mut token_ids = vec![];
+        for _ in 0..3 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            token_ids.push(contract.nft_mint("1".to_string(), accounts(2), None));
+        }
+
+        for token_id in &token_ids[..2] {
+            testing_env!(context
+                .predecessor_account_id(accounts(2))
+                .attached_deposit(STORAGE_APPROVE)
+                .build()
+            );
+            contract.nft_approve(token_id.clone(), accounts(3), None);
+        }
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_APPROVE)
+            .build()
+        );
+        contract.nft_approve(token_ids[2].clone(), accounts(4), None);
+
+        let mut approved = contract.nft_tokens_approved_for(accounts(2), accounts(3), None, None);
+        approved.sort();
+        assert_eq!(approved, token_ids[..2].to_vec());
+        assert_eq!(
+            contract.nft_tokens_approved_for(accounts(2), accounts(4), None, None),
+            vec![token_ids[2].clone()]
+        );
+        assert!(contract.nft_tokens_approved_for(accounts(1), accounts(3), None, None).is_empty());
+    }
+
+    #[test]
+    fn test_nft_buy_min_treasury_cut() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_min_treasury_cut(U128(5));
+        assert_eq!(contract.get_min_treasury_cut(), U128(5));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128(20)), None);
+
+        // 3% of 20 yocto floors to 0
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(20 + STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+        let transfers = get_transfers();
+        assert!(transfers.contains(&(accounts(4).to_string(), 5)));
+        assert!(transfers.contains(&(accounts(1).to_string(), 15)));
+
+        // capped at the price
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_price("1".to_string(), Some(U128(3)));
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(3 + STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+        let transfers = get_transfers();
+        assert!(transfers.contains(&(accounts(4).to_string(), 3)));
+        assert!(transfers.iter().all(|(receiver_id, _)| receiver_id != accounts(1).as_ref()));
+    }
+
+    #[test]
+    fn test_nft_try_get_series_single() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        let series = contract.nft_try_get_series_single("1".to_string()).unwrap();
+        assert_eq!(series.token_series_id, "1");
+        assert_eq!(series.creator_id, accounts(1).to_string());
+        assert!(contract.nft_try_get_series_single("2".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_nft_buy_primary_treasury() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_primary_treasury(accounts(3));
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_secondary_treasury(accounts(5));
+        assert_eq!(contract.get_primary_treasury(), accounts(3).to_string());
+        assert_eq!(contract.get_secondary_treasury(), accounts(5).to_string());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        let price = 10u128.pow(24);
+        create_series(&mut contract, &royalty, Some(U128::from(price)), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(price + STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+
+        let for_treasury = price * 300 / 10_000;
+        let transfers = get_transfers();
+        assert!(transfers.contains(&(accounts(3).to_string(), for_treasury)));
+        assert!(transfers.iter().all(|(receiver_id, _)| receiver_id != accounts(4).as_ref() && receiver_id != accounts(5).as_ref()));
+    }
+
+    #[test]
+    fn test_nft_supply_for_owner_by_series() {
+        let (mut context, mut contract) = setup_contract();
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        for _ in 0..2 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+                .build()
+            );
+            create_series(&mut contract, &royalty, None, None);
+        }
+
+        let mut token_ids = vec![];
+        for (token_series_id, receiver_id) in [("1", accounts(2)), ("1", accounts(2)), ("2", accounts(2)), ("1", accounts(3))] {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            token_ids.push(contract.nft_mint(token_series_id.to_string(), receiver_id, None));
+        }
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(2), "1".to_string()), U64(2));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(2), "2".to_string()), U64(1));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(3), "1".to_string()), U64(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(3), token_ids[0].clone(), None, None);
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(3), token_ids[2].clone(), None, None);
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(2), "1".to_string()), U64(1));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(2), "2".to_string()), U64(0));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(3), "1".to_string()), U64(2));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(3), "2".to_string()), U64(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(2), token_ids[3].clone(), None, None);
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(2), "1".to_string()), U64(2));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(3), "1".to_string()), U64(1));
+    }
+
+    #[test]
+    fn test_nft_mint_and_approve_to_receiver() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT + STORAGE_APPROVE)
+            .build()
+        );
+        assert!(contract.nft_mint_and_approve("1".to_string(), accounts(3), None, Some(accounts(2))).is_none());
+        assert_eq!(
+            get_logs().last().unwrap(),
+            &format!(
+                r#"EVENT_JSON:{{"standard":"nep171","version":"1.0.0","event":"nft_mint","data":[{{"owner_id":"{}","token_ids":["1:1"]}}]}}"#,
+                accounts(2)
+            )
+        );
+
+        let token = contract.nft_token("1:1".to_string()).unwrap();
+        assert_eq!(token.owner_id, accounts(2).to_string());
+        assert!(token.approved_account_ids.unwrap().contains_key(accounts(3).as_ref()));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(1), "1".to_string()), U64(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot approve the receiver")]
+    fn test_invalid_nft_mint_and_approve_receiver() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT + STORAGE_APPROVE)
+            .build()
+        );
+        contract.nft_mint_and_approve("1".to_string(), accounts(2), None, Some(accounts(2)));
+    }
+
+    #[test]
+    fn test_enumeration_enabled() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+
+        assert!(contract.enumeration_enabled());
+        assert_eq!(contract.nft_tokens_for_owner(accounts(2), None, None).len(), 1);
+        assert_eq!(contract.nft_series_for_owner(accounts(2), None, None), vec!["1".to_string()]);
+
+        // owner views degrade to empty results without the index
+        contract.tokens.tokens_per_owner = None;
+        assert!(!contract.enumeration_enabled());
+        assert!(contract.nft_tokens_for_owner(accounts(2), None, None).is_empty());
+        assert!(contract.nft_tokens_for_owner_sorted(accounts(2), None, None).is_empty());
+        assert!(contract.nft_series_for_owner(accounts(2), None, None).is_empty());
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)), U128(0));
+    }
+
+    #[test]
+    fn test_series_id_not_reused() {
+        let (mut context, mut contract) = setup_contract();
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        for _ in 0..2 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+                .build()
+            );
+            create_series(&mut contract, &royalty, None, None);
+        }
+
+        // simulated series deletion
+        contract.token_series_by_id.remove(&"1".to_string());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        create_series(&mut contract, &royalty, None, None);
+        assert_eq!(contract.token_series_by_id.len(), 2);
+        assert!(contract.nft_try_get_series_single("3".to_string()).is_some());
+        assert_eq!(contract.nft_get_series_single("2".to_string()).token_series_id, "2");
+    }
+
+    fn setup_expiring_token() -> (VMContextBuilder, Contract, TokenId) {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_token_lifetime("1".to_string(), Some(3600));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(1_000 * 10u64.pow(9))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        (context, contract, token_id)
+    }
+
+    #[test]
+    fn test_nft_is_expired() {
+        let (mut context, contract, token_id) = setup_expiring_token();
+
+        let token = contract.nft_token(token_id.clone()).unwrap();
+        assert_eq!(token.metadata.unwrap().expires_at, Some((4_600 * 10u64.pow(9)).to_string()));
+        assert!(!contract.nft_is_expired(token_id.clone()));
+
+        testing_env!(context
+            .block_timestamp(4_600 * 10u64.pow(9))
+            .build()
+        );
+        assert!(contract.nft_is_expired(token_id));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token expired")]
+    fn test_invalid_transfer_expired_token() {
+        let (mut context, mut contract, token_id) = setup_expiring_token();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(5_000 * 10u64.pow(9))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(3), token_id, None, None);
+    }
+
+    #[test]
+    fn test_nft_replace_royalty_recipient() {
+        let (mut context, mut contract) = setup_contract();
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(3).to_string(), 500);
+        royalty.insert(accounts(1).to_string(), 300);
+        for _ in 0..2 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+                .build()
+            );
+            create_series(&mut contract, &royalty, None, None);
+        }
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        create_series(&mut contract, &HashMap::new(), None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let updated = contract.nft_replace_royalty_recipient(accounts(3).to_string(), accounts(5), None, None);
+        assert_eq!(updated, vec!["1".to_string(), "2".to_string()]);
+
+        for token_series_id in updated {
+            let royalty = contract.nft_get_series_single(token_series_id).royalty;
+            assert_eq!(royalty.get(accounts(5).as_ref()), Some(&500));
+            assert_eq!(royalty.get(accounts(1).as_ref()), Some(&300));
+            assert!(!royalty.contains_key(accounts(3).as_ref()));
+        }
+        assert!(contract.nft_get_series_single("3".to_string()).royalty.is_empty());
+    }
+
+    #[test]
+    fn test_nft_get_series_sorted() {
+        let (mut context, mut contract) = setup_contract();
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        for _ in 0..11 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+                .build()
+            );
+            create_series(&mut contract, &royalty, None, None);
+        }
+        // swap_remove moves the last series into the freed slot of the map
+        contract.token_series_by_id.remove(&"2".to_string());
+
+        let mut token_series_ids: Vec<TokenSeriesId> = contract
+            .nft_get_series_sorted(None, Some(5), None)
+            .into_iter()
+            .map(|series| series.token_series_id)
+            .collect();
+        token_series_ids.extend(
+            contract
+                .nft_get_series_sorted(Some(U128(5)), Some(5), None)
+                .into_iter()
+                .map(|series| series.token_series_id),
+        );
+        let expected: Vec<TokenSeriesId> = (1..=11)
+            .filter(|id| *id != 2)
+            .map(|id| id.to_string())
+            .collect();
+        assert_eq!(token_series_ids, expected);
+    }
+
+    #[test]
+    fn test_series_views_effective_mintable() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        let start_time: Timestamp = 1618109122863866400;
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(start_time)
+            .attached_deposit(1)
+            .build()
+        );
+        let non_mintable_at = contract.nft_request_non_mintable("1".to_string());
+        assert!(contract.nft_get_series_single("1".to_string()).is_mintable);
+
+        testing_env!(context
+            .block_timestamp(u64::from(non_mintable_at) * 10u64.pow(9))
+            .build()
+        );
+        assert!(contract.token_series_by_id.get(&"1".to_string()).unwrap().is_mintable);
+        assert!(!contract.nft_get_series_single("1".to_string()).is_mintable);
+        assert!(!contract.nft_get_series(None, None, None)[0].is_mintable);
+        assert!(!contract.nft_get_series_sorted(None, None, None)[0].is_mintable);
+        assert!(!contract.nft_export_series("1".to_string()).is_mintable);
+    }
+
+    #[test]
+    fn test_nft_payout_batch() {
+        let (mut context, mut contract) = setup_contract();
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        create_series(&mut contract, &royalty, None, None);
+        royalty.insert(accounts(3).to_string(), 500);
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        create_series(&mut contract, &royalty, None, None);
+
+        let mut token_ids = vec![];
+        for token_series_id in ["1", "2"] {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            token_ids.push(contract.nft_mint(token_series_id.to_string(), accounts(2), None));
+        }
+
+        let balances = vec![U128(10_000), U128(3_000)];
+        let payouts = contract.nft_payout_batch(token_ids.clone(), balances.clone(), 10);
+        assert_eq!(payouts.len(), 2);
+        for ((token_id, balance), payout) in token_ids.into_iter().zip(balances).zip(payouts) {
+            assert_eq!(payout.payout, contract.nft_payout(token_id, balance, 10).payout);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Token ids and balances length mismatch")]
+    fn test_invalid_nft_payout_batch_length_mismatch() {
+        let (_, contract) = setup_contract();
+        contract.nft_payout_batch(vec!["1:1".to_string()], vec![], 10);
+    }
+
+    /// accounts(2) holds "1:1" and the owner has a pending payout of 300 when the contract retires
+    fn setup_retired_contract() -> (VMContextBuilder, Contract) {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            VMConfig::default(),
+            RuntimeFeesConfig::default(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        contract.on_treasury_transfer_failed(U128(300));
+
+        let start_time: Timestamp = 1618109122863866400;
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(start_time)
+            .attached_deposit(1)
+            .build()
+        );
+        let retirement_available_at = contract.request_retirement();
+        assert_eq!(retirement_available_at, to_sec(start_time) + RETIREMENT_TIMELOCK);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(u64::from(retirement_available_at) * 10u64.pow(9))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.retire();
+        assert!(contract.is_retired());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_retired_contract_allows_exit() {
+        let (mut context, mut contract) = setup_retired_contract();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(3), "1:1".to_string(), None, None);
+        assert_eq!(contract.nft_token("1:1".to_string()).unwrap().owner_id, accounts(3).to_string());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        assert_eq!(contract.claim_pending_payout(), U128(300));
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract retired")]
+    fn test_invalid_mint_after_retire() {
+        let (mut context, mut contract) = setup_retired_contract();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract retired")]
+    fn test_invalid_create_series_after_retire() {
+        let (mut context, mut contract) = setup_retired_contract();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        create_series(&mut contract, &HashMap::new(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Retirement timelock active")]
+    fn test_invalid_retire_before_timelock() {
+        let (mut context, mut contract) = setup_contract();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.request_retirement();
+        contract.retire();
+    }
+
+    #[test]
+    fn test_nft_next_approval_id() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        assert_eq!(contract.nft_next_approval_id(token_id.clone()), U64(1));
+
+        for (market_id, next_approval_id) in [(accounts(3), 2), (accounts(4), 3)] {
+            testing_env!(context
+                .predecessor_account_id(accounts(2))
+                .attached_deposit(STORAGE_APPROVE)
+                .build()
+            );
+            contract.nft_approve(token_id.clone(), market_id.clone(), None);
+            let approvals = contract.nft_token(token_id.clone()).unwrap().approved_account_ids.unwrap();
+            assert_eq!(approvals.get(market_id.as_ref()), Some(&(next_approval_id - 1)));
+            assert_eq!(contract.nft_next_approval_id(token_id.clone()), U64(next_approval_id));
+        }
+    }
+
+    #[test]
+    fn test_nft_quote_buy() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        let price = 10u128.pow(24);
+        create_series(&mut contract, &royalty, Some(U128::from(price)), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(price + STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+
+        let storage_cost = env::storage_byte_cost() * Balance::from(contract.last_buy_storage_usage);
+        assert_eq!(contract.nft_quote_buy("1".to_string()), U128(price + storage_cost));
+    }
+
+    #[test]
+    #[should_panic(expected = "yoctoNEAR to buy (price 1000000000000000000000000 + storage ")]
+    fn test_invalid_nft_buy_without_storage_margin() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        let price = 10u128.pow(24);
+        create_series(&mut contract, &royalty, Some(U128::from(price)), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(price)
+            .build()
+        );
+        contract.nft_buy("1".to_string(), accounts(2), None, None);
+    }
+
+    #[test]
+    fn test_nft_series_sales() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        let price = 10u128.pow(24);
+        create_series(&mut contract, &royalty, Some(U128::from(price)), None);
+
+        let mut token_ids = vec![];
+        for _ in 0..2 {
+            testing_env!(context
+                .predecessor_account_id(accounts(2))
+                .attached_deposit(price + STORAGE_FOR_MINT)
+                .build()
+            );
+            token_ids.push(contract.nft_buy("1".to_string(), accounts(2), None, None));
+        }
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(3), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_burn(token_ids[0].clone());
+
+        assert_eq!(contract.nft_series_sales("1".to_string()), SeriesSales {
+            total_sold: U64(2),
+            minted_count: U64(3),
+            supply: U64(2),
+        });
+    }
+
+    fn write_released_state(market_fee: Option<u128>, editions: u64) {
+        let metadata = NFTContractMetadata {
+            spec: NFT_METADATA_SPEC.to_string(),
+            name: "Apollo42".to_string(),
+            symbol: "A42".to_string(),
+            icon: None,
+            base_uri: None,
+            reference: None,
+            reference_hash: None,
+        };
+        let mut market_data_transaction_fee = MarketDataTransactionFee{
+            transaction_fee: UnorderedMap::new(StorageKey::MarketDataTransactionFee)
+        };
+        if let Some(market_fee) = market_fee {
+            market_data_transaction_fee.transaction_fee.insert(&"1".to_string(), &market_fee);
+        }
+        let mut prev = ContractV2 {
+            tokens: NonFungibleToken::new(
+                StorageKey::NonFungibleToken,
+                accounts(0),
+                Some(StorageKey::TokenMetadata),
+                Some(StorageKey::Enumeration),
+                Some(StorageKey::Approval),
+            ),
+            metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
+            token_series_by_id: UnorderedMap::new(StorageKey::TokenSeriesById),
+            treasury_id: accounts(4).to_string(),
+            transaction_fee: TransactionFee {
+                next_fee: None,
+                start_time: None,
+                current_fee: 300,
+            },
+            market_data_transaction_fee,
+        };
+        for token_series_id in ["1", "2"] {
+            let mut tokens = UnorderedSet::new(
+                StorageKey::TokensBySeriesInner {
+                    token_series: token_series_id.to_string(),
+                }
+                .try_to_vec()
+                .unwrap(),
+            );
+            let tokens_per_owner = prev.tokens.tokens_per_owner.as_mut().unwrap();
+            let mut token_ids = tokens_per_owner.get(accounts(2).as_ref()).unwrap_or_else(|| {
+                UnorderedSet::new(StorageKey::TokensPerOwner {
+                    account_hash: env::sha256(accounts(2).as_ref().as_bytes()),
+                })
+            });
+            for edition in 1..=editions {
+                let token_id = format!("{}{}{}", token_series_id, TOKEN_DELIMETER, edition);
+                tokens.insert(&token_id);
+                prev.tokens.owner_by_id.insert(&token_id, &accounts(2).to_string());
+                token_ids.insert(&token_id);
+            }
+            tokens_per_owner.insert(accounts(2).as_ref(), &token_ids);
+            prev.token_series_by_id.insert(&token_series_id.to_string(), &TokenSeriesV1 {
+                metadata: sample_token_metadata(None),
+                creator_id: accounts(1).to_string(),
+                tokens,
+                price: Some(10u128.pow(24)),
+                is_mintable: true,
+                royalty: HashMap::new(),
+            });
+        }
+        env::state_write(&prev);
+    }
+
+    #[test]
+    fn test_migrate_from_released_state() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        write_released_state(Some(100), 1);
+
+        let mut contract = Contract::migrate();
+        assert_eq!(contract.get_primary_treasury(), accounts(4).to_string());
+        assert_eq!(contract.market_data_transaction_fee.transaction_fee.get(&"1".to_string()), Some(100));
+        assert_eq!(contract.get_transaction_fee().current_fee, 300);
+        assert_eq!(contract.nft_total_minted(), U128(2));
+        assert_eq!(contract.nft_total_burned(), U128(0));
+
+        assert_eq!(contract.migrate_series(1), U64(1));
+        assert_eq!(contract.migrate_series(10), U64(0));
+        assert_eq!(contract.migrate_series(10), U64(0));
+
+        let series = contract.nft_get_series_single("2".to_string());
+        assert_eq!(series.creator_id, accounts(1).to_string());
+        assert!(series.is_mintable);
+        assert_eq!(contract.nft_supply_for_series("1".to_string()), U64(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        assert_eq!(contract.nft_mint("1".to_string(), accounts(2), None), "1:2");
+        assert_eq!(contract.nft_total_minted(), U128(3));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        create_series(&mut contract, &HashMap::new(), None, None);
+        assert!(contract.nft_try_get_series_single("3".to_string()).is_some());
+    }
+
+    #[test]
+    fn test_migrate_series_next_edition() {
+        let mut context = get_context(accounts(0));
+        // writing the released state of a large series is test setup, not a contract call
+        testing_env!(
+            context.build(),
+            VMConfig::free(),
+            RuntimeFeesConfig::free(),
+            Default::default(),
+            vec![]
+        );
+        write_released_state(None, 300);
+
+        // a fresh gas allowance, the same as a separate migrate transaction
+        testing_env!(context.build());
+        let mut contract = Contract::migrate();
+        contract.migrate_series(10);
+        assert_eq!(contract.token_series_by_id.get(&"1".to_string()).unwrap().next_edition, 301);
+        assert_eq!(contract.nft_next_token_id("1".to_string()), Some("1:301".to_string()));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        assert_eq!(contract.nft_mint("1".to_string(), accounts(2), None), "1:301");
+    }
+
+    #[test]
+    fn test_backfill_owner_series_counts() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        write_released_state(None, 1);
+
+        let mut contract = Contract::migrate();
+        contract.migrate_series(10);
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(2), "1".to_string()), U64(1));
+
+        assert!(!contract.backfill_owner_series_counts(1));
+
+        // "1:2" is past the cursor, the backfill counts it
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_mint("1".to_string(), accounts(2), None);
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(2), "1".to_string()), U64(2));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(0)
+            .build()
+        );
+        assert!(contract.backfill_owner_series_counts(10));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(2), "1".to_string()), U64(2));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(2), "2".to_string()), U64(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(3), "1:1".to_string(), None, None);
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(2), "1".to_string()), U64(1));
+        assert_eq!(contract.nft_supply_for_owner_by_series(accounts(3), "1".to_string()), U64(1));
+    }
+
+    #[test]
+    fn test_migrate_from_v1_state() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let prev = ContractV1 {
+            tokens: NonFungibleToken::new(
+                StorageKey::NonFungibleToken,
+                accounts(0),
+                Some(StorageKey::TokenMetadata),
+                Some(StorageKey::Enumeration),
+                Some(StorageKey::Approval),
+            ),
+            metadata: LazyOption::new(StorageKey::Metadata, None),
+            token_series_by_id: UnorderedMap::new(StorageKey::TokenSeriesById),
+            treasury_id: accounts(4).to_string(),
+            transaction_fee: TransactionFee {
+                next_fee: None,
+                start_time: None,
+                current_fee: 300,
+            },
+        };
+        env::state_write(&prev);
+
+        let contract = Contract::migrate();
+        assert_eq!(contract.get_primary_treasury(), accounts(4).to_string());
+        assert!(contract.market_data_transaction_fee.transaction_fee.is_empty());
+    }
+
+    #[test]
+    fn test_nft_has_minted() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        assert!(contract.nft_has_minted(accounts(2), "1".to_string()));
+        assert!(!contract.nft_has_minted(accounts(3), "1".to_string()));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(3), token_id, None, None);
+
+        assert!(contract.nft_has_minted(accounts(2), "1".to_string()));
+        assert!(!contract.nft_has_minted(accounts(3), "1".to_string()));
+        assert!(!contract.nft_has_minted(accounts(2), "2".to_string()));
+    }
+
+    #[test]
+    fn test_nft_total_minted_and_burned() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+
+        let mut token_ids = vec![];
+        for _ in 0..5 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            token_ids.push(contract.nft_mint("1".to_string(), accounts(2), None));
+        }
 
-    pub fn nft_total_supply(&self) -> U128 {
-        (self.tokens.owner_by_id.len() as u128).into()
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_burn(token_ids[0].clone());
+        contract.nft_burn(token_ids[1].clone());
+
+        assert_eq!(contract.nft_total_minted(), U128::from(5));
+        assert_eq!(contract.nft_total_burned(), U128::from(2));
+        assert_eq!(contract.nft_total_supply(), U128::from(3));
     }
 
-    pub fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
-        // Get starting index, whether or not it was explicitly given.
-        // Defaults to 0 based on the spec:
-        // https://nomicon.io/Standards/NonFungibleToken/Enumeration.html#interface
-        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
-        assert!(
-            (self.tokens.owner_by_id.len() as u128) > start_index,
-            "Out of bounds, please use a smaller from_index."
+    #[test]
+    fn test_nft_burn_not_burnable_by_creator() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
         );
-        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
-        assert_ne!(limit, 0, "Cannot provide limit of 0.");
-        self.tokens
-            .owner_by_id
-            .iter()
-            .skip(start_index as usize)
-            .take(limit)
-            .map(|(token_id, _)| self.nft_token(token_id).unwrap())
-            .collect()
-    }
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
 
-    pub fn nft_supply_for_owner(self, account_id: ValidAccountId) -> U128 {
-        let tokens_per_owner = self.tokens.tokens_per_owner.expect(
-            "Could not find tokens_per_owner when calling a method on the enumeration standard.",
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
         );
-        tokens_per_owner
-            .get(account_id.as_ref())
-            .map(|account_tokens| U128::from(account_tokens.len() as u128))
-            .unwrap_or(U128(0))
-    }
+        contract.nft_set_series_burnable_by_owner("1".to_string(), false);
+        assert!(!contract.nft_get_series_single("1".to_string()).burnable_by_owner);
 
-    pub fn nft_tokens_for_owner(
-        &self,
-        account_id: ValidAccountId,
-        from_index: Option<U128>,
-        limit: Option<u64>,
-    ) -> Vec<Token> {
-        let tokens_per_owner = self.tokens.tokens_per_owner.as_ref().expect(
-            "Could not find tokens_per_owner when calling a method on the enumeration standard.",
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
         );
-        let token_set = if let Some(token_set) = tokens_per_owner.get(account_id.as_ref()) {
-            token_set
-        } else {
-            return vec![];
-        };
-        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
-        assert_ne!(limit, 0, "Cannot provide limit of 0.");
-        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
-        assert!(
-            token_set.len() as u128 > start_index,
-            "Out of bounds, please use a smaller from_index."
+        let token_id = contract.nft_mint("1".to_string(), accounts(1), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
         );
-        token_set
-            .iter()
-            .skip(start_index as usize)
-            .take(limit)
-            .map(|token_id| self.nft_token(token_id).unwrap())
-            .collect()
+        contract.nft_burn(token_id.clone());
+        assert!(contract.nft_token(token_id).is_none());
     }
 
-    pub fn nft_payout(
-        &self, 
-        token_id: TokenId,
-        balance: U128, 
-        max_len_payout: u32
-    ) -> Payout{
-        let owner_id = self.tokens.owner_by_id.get(&token_id).expect("No token id");
-        let mut token_id_iter = token_id.split(TOKEN_DELIMETER);
-        let token_series_id = token_id_iter.next().unwrap().parse().unwrap();
-        let royalty = self.token_series_by_id.get(&token_series_id).expect("no type").royalty;
-
-        assert!(royalty.len() as u32 <= max_len_payout, "Market cannot payout to that many receivers");
+    #[test]
+    #[should_panic(expected = "Series not burnable")]
+    fn test_invalid_nft_burn_not_burnable_by_holder() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
 
-        let balance_u128: u128 = balance.into();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_burnable_by_owner("1".to_string(), false);
 
-        let mut payout: Payout = Payout { payout: HashMap::new() };
-        let mut total_perpetual = 0;
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
 
-        for (k, v) in royalty.iter() {
-            if *k != owner_id {
-                let key = k.clone();
-                payout.payout.insert(key, royalty_to_payout(*v, balance_u128));
-                total_perpetual += *v;
-            }
-        }
-        payout.payout.insert(owner_id, royalty_to_payout(10000 - total_perpetual, balance_u128));
-        payout
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_burn(token_id);
     }
 
-    #[payable]
-    pub fn nft_transfer_payout(
-        &mut self, 
-        receiver_id: ValidAccountId,
-        token_id: TokenId,
-        approval_id: Option<u64>,
-        balance: Option<U128>,
-        max_len_payout: Option<u32>
-    ) -> Option<Payout> {
-        assert_one_yocto();
-
-        let sender_id = env::predecessor_account_id();
-        // Transfer
-        let previous_token = self.nft_token(token_id.clone()).expect("no token");
-        self.tokens.nft_transfer(receiver_id.clone(), token_id.clone(), approval_id, None);
-
-        // Payout calculation
-        let previous_owner_id = previous_token.owner_id;
-        let mut total_perpetual = 0;
-        let payout = if let Some(balance) = balance {
-            let balance_u128: u128 = u128::from(balance);
-            let mut payout: Payout = Payout { payout: HashMap::new() };
-
-            let mut token_id_iter = token_id.split(TOKEN_DELIMETER);
-            let token_series_id = token_id_iter.next().unwrap().parse().unwrap();
-            let royalty = self.token_series_by_id.get(&token_series_id).expect("no type").royalty;
+    #[test]
+    fn test_nft_creator_burn() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
 
-            assert!(royalty.len() as u32 <= max_len_payout.unwrap(), "Market cannot payout to that many receivers");
-            for (k, v) in royalty.iter() {
-                let key = k.clone();
-                if key != previous_owner_id {
-                    payout.payout.insert(key, royalty_to_payout(*v, balance_u128));
-                    total_perpetual += *v;
-                }
-            }
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_burnable_by_owner("1".to_string(), false);
 
-            assert!(
-                total_perpetual <= 10000,
-                "Total payout overflow"
-            );
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(1), None);
 
-            payout.payout.insert(previous_owner_id.clone(), royalty_to_payout(10000 - total_perpetual, balance_u128));
-            Some(payout)
-        } else {
-            None
-        };
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_creator_burn(token_id.clone());
 
-        let authorized_id : Option<AccountId> = if sender_id != previous_owner_id {
-            Some(sender_id)
-        } else {
-            None
-        };
+        assert!(contract.nft_token(token_id.clone()).is_none());
+        assert!(contract.tokens.token_metadata_by_id.as_ref().unwrap().get(&token_id).is_none());
+        assert_eq!(contract.nft_total_supply(), U128::from(0));
+        assert_eq!(contract.nft_supply_for_owner(accounts(1)), U128::from(0));
+    }
 
-        NearEvent::log_nft_transfer(
-            previous_owner_id,
-            receiver_id.to_string(),
-            vec![token_id],
-            None,
-            authorized_id,
+    #[test]
+    #[should_panic(expected = "Token owner only")]
+    fn test_invalid_nft_creator_burn_not_held() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
         );
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
 
-        payout
-    }
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
 
-    pub fn get_owner(&self) -> AccountId {
-        self.tokens.owner_id.clone()
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_creator_burn(token_id);
     }
-}
 
-fn royalty_to_payout(a: u32, b: Balance) -> U128 {
-    U128(a as u128 * b / 10_000u128)
-}
+    #[test]
+    fn test_nft_burn_and_mint() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
 
-// near_contract_standards::impl_non_fungible_token_core!(Contract, tokens);
-// near_contract_standards::impl_non_fungible_token_enumeration!(Contract, tokens);
-near_contract_standards::impl_non_fungible_token_approval!(Contract, tokens);
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
 
-#[near_bindgen]
-impl NonFungibleTokenMetadataProvider for Contract {
-    fn nft_metadata(&self) -> NFTContractMetadata {
-        self.metadata.get().unwrap()
-    }
-}
+        create_series(&mut contract, &royalty, None, None);
+        create_series(&mut contract, &royalty, None, None);
 
-#[near_bindgen]
-impl NonFungibleTokenResolver for Contract {
-    #[private]
-    fn nft_resolve_transfer(
-        &mut self,
-        previous_owner_id: AccountId,
-        receiver_id: AccountId,
-        token_id: TokenId,
-        approved_account_ids: Option<HashMap<AccountId, u64>>,
-    ) -> bool {
-        let resp: bool = self.tokens.nft_resolve_transfer(
-            previous_owner_id.clone(),
-            receiver_id.clone(),
-            token_id.clone(),
-            approved_account_ids,
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
         );
 
-        // if not successful, return nft back to original owner
-        if !resp {
-            NearEvent::log_nft_transfer(
-                receiver_id,
-                previous_owner_id,
-                vec![token_id],
-                None,
-                None,
-            );
-        }
+        contract.nft_set_series_upgrade_inputs("2".to_string(), vec!["1".to_string()]);
 
-        resp
-    }
-}
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
 
-/// from https://github.com/near/near-sdk-rs/blob/e4abb739ff953b06d718037aa1b8ab768db17348/near-contract-standards/src/non_fungible_token/utils.rs#L29
+        let first_token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        let second_token_id = contract.nft_mint("1".to_string(), accounts(2), None);
 
-fn refund_deposit(storage_used: u64, extra_spend: Balance) {
-    let required_cost = env::storage_byte_cost() * Balance::from(storage_used);
-    let attached_deposit = env::attached_deposit() - extra_spend;
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
 
-    assert!(
-        required_cost <= attached_deposit,
-        "Must attach {} yoctoNEAR to cover storage",
-        required_cost,
-    );
+        let token_id = contract.nft_burn_and_mint(
+            vec![first_token_id.clone(), second_token_id.clone()],
+            "2".to_string(),
+            accounts(2),
+        );
 
-    let refund = attached_deposit - required_cost;
-    if refund > 1 {
-        Promise::new(env::predecessor_account_id()).transfer(refund);
+        assert!(contract.nft_token(first_token_id).is_none());
+        assert!(contract.nft_token(second_token_id).is_none());
+        assert_eq!(token_id, "2:1".to_string());
+        assert_eq!(contract.nft_token(token_id).unwrap().owner_id, accounts(2).to_string());
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)), U128::from(1));
     }
-}
-
-fn to_sec(timestamp: Timestamp) -> TimestampSec {
-    (timestamp / 10u64.pow(9)) as u32
-}
 
-#[cfg(all(test, not(target_arch = "wasm32")))]
-mod tests {
-    use super::*;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::MockedBlockchain;
-    use near_sdk::{testing_env};
+    #[test]
+    #[should_panic(expected = "Input series must have the same creator")]
+    fn test_invalid_nft_set_series_upgrade_inputs_other_creator() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
 
-    const STORAGE_FOR_CREATE_SERIES: Balance = 8540000000000000000000;
-    const STORAGE_FOR_MINT: Balance = 11280000000000000000000;
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
 
-    fn get_context(predecessor_account_id: ValidAccountId) -> VMContextBuilder {
-        let mut builder = VMContextBuilder::new();
-        builder
-            .current_account_id(accounts(0))
-            .signer_account_id(predecessor_account_id.clone())
-            .predecessor_account_id(predecessor_account_id);
-        builder
-    }
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+        create_series(&mut contract, &royalty, None, None);
 
-    fn setup_contract() -> (VMContextBuilder, Contract) {
-        let mut context = VMContextBuilder::new();
-        testing_env!(context.predecessor_account_id(accounts(0)).build());
-        let contract = Contract::new_default_meta(accounts(0), accounts(4));
-        (context, contract)
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_upgrade_inputs("2".to_string(), vec!["1".to_string()]);
     }
 
     #[test]
-    fn test_new() {
-        let mut context = get_context(accounts(1));
-        testing_env!(context.build());
-        let contract = Contract::new(
-            accounts(1),
-            accounts(4),
-            NFTContractMetadata {
-                spec: NFT_METADATA_SPEC.to_string(),
-                name: "Triple Triad".to_string(),
-                symbol: "TRIAD".to_string(),
-                icon: Some(DATA_IMAGE_SVG_APOLLO42_ICON.to_string()),
-                base_uri: Some("https://lh3.googleusercontent.com/".to_string()),
-                reference: None,
-                reference_hash: None,
-            },
-            500
+    #[should_panic(expected = "Series not burnable")]
+    fn test_invalid_nft_burn_and_mint_not_burnable() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
         );
-        testing_env!(context.is_view(true).build());
-        assert_eq!(contract.get_owner(), accounts(1).to_string());
-        assert_eq!(contract.nft_metadata().base_uri.unwrap(), "https://lh3.googleusercontent.com/".to_string());
-        assert_eq!(contract.nft_metadata().icon.unwrap(), DATA_IMAGE_SVG_APOLLO42_ICON.to_string());
-    }
 
-    fn create_series(
-        contract: &mut Contract,
-        royalty: &HashMap<AccountId, u32>,
-        price: Option<U128>,
-        copies: Option<u64>,
-    ) {
-        contract.nft_create_series(
-            None,
-            TokenMetadata {
-                title: Some("Tsundere land".to_string()),
-                description: None,
-                media: Some(
-                    "bafybeidzcan4nzcz7sczs4yzyxly4galgygnbjewipj6haco4kffoqpkiy".to_string()
-                ),
-                media_hash: None,
-                copies: copies,
-                issued_at: None,
-                expires_at: None,
-                starts_at: None,
-                updated_at: None,
-                extra: None,
-                reference: Some(
-                    "bafybeicg4ss7qh5odijfn2eogizuxkrdh3zlv4eftcmgnljwu7dm64uwji".to_string()
-                ),
-                reference_hash: None,
-            },
-            price,
-            Some(royalty.clone()),
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_set_series_upgrade_inputs("2".to_string(), vec!["1".to_string()]);
+        contract.nft_set_series_burnable_by_owner("1".to_string(), false);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
         );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
+        );
+        contract.nft_burn_and_mint(vec![token_id], "2".to_string(), accounts(2));
     }
 
     #[test]
-    fn test_create_series() {
+    #[should_panic(expected = "Token 2:1 is not an upgrade input")]
+    fn test_invalid_nft_burn_and_mint_not_input() {
         let (mut context, mut contract) = setup_contract();
         testing_env!(context
             .predecessor_account_id(accounts(1))
@@ -1308,47 +10588,38 @@ mod tests {
 
         let mut royalty: HashMap<AccountId, u32> = HashMap::new();
         royalty.insert(accounts(1).to_string(), 1000);
-        create_series(
-            &mut contract,
-            &royalty,
-            Some(U128::from(1 * 10u128.pow(24))),
-            None
-        );
 
-        let nft_series_return = contract.nft_get_series_single("1".to_string());
-        assert_eq!(
-            nft_series_return.creator_id,
-            accounts(1).to_string()
-        );
+        create_series(&mut contract, &royalty, None, None);
+        create_series(&mut contract, &royalty, None, None);
+        create_series(&mut contract, &royalty, None, None);
 
-        assert_eq!(
-            nft_series_return.token_series_id,
-            "1",
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
         );
 
-        assert_eq!(
-            nft_series_return.royalty,
-            royalty,
-        );
+        contract.nft_set_series_upgrade_inputs("3".to_string(), vec!["1".to_string()]);
 
-        assert!(
-            nft_series_return.metadata.copies.is_none()
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
         );
 
-        assert_eq!(
-            nft_series_return.metadata.title.unwrap(),
-            "Tsundere land".to_string()
-        );
+        let token_id = contract.nft_mint("2".to_string(), accounts(2), None);
 
-        assert_eq!(
-            nft_series_return.metadata.reference.unwrap(),
-            "bafybeicg4ss7qh5odijfn2eogizuxkrdh3zlv4eftcmgnljwu7dm64uwji".to_string()
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
         );
 
+        contract.nft_burn_and_mint(vec![token_id], "3".to_string(), accounts(2));
     }
 
     #[test]
-    fn test_buy() {
+    fn test_nft_transfer() {
         let (mut context, mut contract) = setup_contract();
         testing_env!(context
             .predecessor_account_id(accounts(1))
@@ -1359,30 +10630,33 @@ mod tests {
         let mut royalty: HashMap<AccountId, u32> = HashMap::new();
         royalty.insert(accounts(1).to_string(), 1000);
 
-        create_series(
-            &mut contract,
-            &royalty,
-            Some(U128::from(1 * 10u128.pow(24))),
-            None
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build()
         );
 
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+
         testing_env!(context
             .predecessor_account_id(accounts(2))
-            .attached_deposit(1 * 10u128.pow(24) + STORAGE_FOR_MINT)
+            .attached_deposit(1)
             .build()
         );
 
-        let token_id = contract.nft_buy("1".to_string(), accounts(2));
+        contract.nft_transfer(accounts(3), token_id.clone(), None, None);
 
-        let token_from_nft_token = contract.nft_token(token_id);
+        let token = contract.nft_token(token_id).unwrap();
         assert_eq!(
-            token_from_nft_token.unwrap().owner_id,
-            accounts(2).to_string()
+            token.owner_id,
+            accounts(3).to_string()
         )
     }
 
     #[test]
-    fn test_mint() {
+    fn test_nft_transfer_unsafe() {
         let (mut context, mut contract) = setup_contract();
         testing_env!(context
             .predecessor_account_id(accounts(1))
@@ -1401,18 +10675,24 @@ mod tests {
             .build()
         );
 
-        let token_id = contract.nft_mint("1".to_string(), accounts(2));
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
 
-        let token_from_nft_token = contract.nft_token(token_id);
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .build()
+        );
+
+        contract.nft_transfer_unsafe(accounts(3), token_id.clone(), None, None);
+
+        let token = contract.nft_token(token_id).unwrap();
         assert_eq!(
-            token_from_nft_token.unwrap().owner_id,
-            accounts(2).to_string()
+            token.owner_id,
+            accounts(3).to_string()
         )
     }
 
     #[test]
-    #[should_panic(expected = "Token series is not mintable")]
-    fn test_invalid_mint_non_mintable() {
+    fn test_nft_transfer_payout() {
         let (mut context, mut contract) = setup_contract();
         testing_env!(context
             .predecessor_account_id(accounts(1))
@@ -1427,47 +10707,117 @@ mod tests {
 
         testing_env!(context
             .predecessor_account_id(accounts(1))
-            .attached_deposit(1)
+            .attached_deposit(STORAGE_FOR_MINT)
             .build()
         );
-        contract.nft_set_series_non_mintable("1".to_string());
+
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
 
         testing_env!(context
-            .predecessor_account_id(accounts(1))
-            .attached_deposit(STORAGE_FOR_MINT)
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
             .build()
         );
 
-        contract.nft_mint("1".to_string(), accounts(2));
+        let payout = contract.nft_transfer_payout(
+            accounts(3),
+            token_id.clone(),
+            Some(0) ,
+            Some(U128::from(1 * 10u128.pow(24))),
+            Some(10)
+        );
+
+        let mut payout_calc: HashMap<AccountId, U128> = HashMap::new();
+        payout_calc.insert(
+            accounts(1).to_string(),
+            U128::from((1000 * (1 * 10u128.pow(24)))/10_000)
+        );
+        payout_calc.insert(
+            accounts(2).to_string(),
+            U128::from((9000 * (1 * 10u128.pow(24))) / 10_000)
+        );
+
+        assert_eq!(payout.unwrap().payout, payout_calc);
+
+        let token = contract.nft_token(token_id).unwrap();
+        assert_eq!(
+            token.owner_id,
+            accounts(3).to_string()
+        )
     }
 
     #[test]
-    #[should_panic(expected = "Token series is not mintable")]
-    fn test_invalid_mint_above_copies() {
+    fn test_nft_payout_rounding() {
+        let (mut context, mut contract) = setup_contract();
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1500);
+
+        // 1500 bps of 1005 is 150.75
+        let cases = [
+            (RoundingMode::Floor, 150u128),
+            (RoundingMode::Round, 151u128),
+            (RoundingMode::Ceil, 151u128),
+        ];
+        for (index, (rounding, expected)) in cases.iter().enumerate() {
+            let token_series_id = (index + 1).to_string();
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+                .build()
+            );
+            create_series(&mut contract, &royalty, None, None);
+
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(1)
+                .build()
+            );
+            contract.nft_set_series_royalty_rounding(token_series_id.clone(), *rounding);
+
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            let token_id = contract.nft_mint(token_series_id, accounts(2), None);
+
+            let payout = contract.nft_payout(token_id, U128(1005), 10).payout;
+            assert_eq!(payout.get(&accounts(1).to_string()).unwrap().0, *expected);
+            assert_eq!(payout.get(&accounts(2).to_string()).unwrap().0, 1005 - *expected);
+            assert_eq!(payout.values().map(|amount| amount.0).sum::<u128>(), 1005);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_invalid_nft_set_token_royalty_no_deposit() {
         let (mut context, mut contract) = setup_contract();
         testing_env!(context
             .predecessor_account_id(accounts(1))
             .attached_deposit(STORAGE_FOR_CREATE_SERIES)
             .build()
         );
-
-        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
-        royalty.insert(accounts(1).to_string(), 1000);
-
-        create_series(&mut contract, &royalty, None, Some(1));
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, None, None);
 
         testing_env!(context
             .predecessor_account_id(accounts(1))
             .attached_deposit(STORAGE_FOR_MINT)
             .build()
         );
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
 
-        contract.nft_mint("1".to_string(), accounts(2));
-        contract.nft_mint("1".to_string(), accounts(2));
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build()
+        );
+        contract.nft_set_token_royalty(token_id, None);
     }
 
     #[test]
-    fn test_decrease_copies() {
+    fn test_nft_payout_token_royalty_override() {
         let (mut context, mut contract) = setup_contract();
         testing_env!(context
             .predecessor_account_id(accounts(1))
@@ -1477,30 +10827,40 @@ mod tests {
 
         let mut royalty: HashMap<AccountId, u32> = HashMap::new();
         royalty.insert(accounts(1).to_string(), 1000);
+        create_series(&mut contract, &royalty, None, None);
 
-        create_series(&mut contract, &royalty, None, Some(5));
+        let mut token_ids = vec![];
+        for _ in 0..2 {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(STORAGE_FOR_MINT)
+                .build()
+            );
+            token_ids.push(contract.nft_mint("1".to_string(), accounts(2), None));
+        }
 
+        let mut token_royalty: HashMap<AccountId, u32> = HashMap::new();
+        token_royalty.insert(accounts(3).to_string(), 2500);
         testing_env!(context
             .predecessor_account_id(accounts(1))
-            .attached_deposit(STORAGE_FOR_MINT)
+            .attached_deposit(STORAGE_APPROVE)
             .build()
         );
+        contract.nft_set_token_royalty(token_ids[0].clone(), Some(token_royalty));
 
-        contract.nft_mint("1".to_string(), accounts(2));
-        contract.nft_mint("1".to_string(), accounts(2));
-
-        testing_env!(context
-            .predecessor_account_id(accounts(1))
-            .attached_deposit(1)
-            .build()
-        );
+        let payout = contract.nft_payout(token_ids[0].clone(), U128(10_000), 10).payout;
+        assert_eq!(payout.get(&accounts(3).to_string()).unwrap().0, 2500);
+        assert!(!payout.contains_key(&accounts(1).to_string()));
+        assert_eq!(payout.get(&accounts(2).to_string()).unwrap().0, 7500);
 
-        contract.nft_decrease_series_copies("1".to_string(), U64::from(3));
+        let payout = contract.nft_payout(token_ids[1].clone(), U128(10_000), 10).payout;
+        assert_eq!(payout.get(&accounts(1).to_string()).unwrap().0, 1000);
+        assert!(!payout.contains_key(&accounts(3).to_string()));
+        assert_eq!(payout.get(&accounts(2).to_string()).unwrap().0, 9000);
     }
 
     #[test]
-    #[should_panic(expected = "Cannot decrease supply, already minted: 2")]
-    fn test_invalid_decrease_copies() {
+    fn test_nft_transfer_payout_creator_is_seller() {
         let (mut context, mut contract) = setup_contract();
         testing_env!(context
             .predecessor_account_id(accounts(1))
@@ -1511,7 +10871,7 @@ mod tests {
         let mut royalty: HashMap<AccountId, u32> = HashMap::new();
         royalty.insert(accounts(1).to_string(), 1000);
 
-        create_series(&mut contract, &royalty, None, Some(5));
+        create_series(&mut contract, &royalty, None, None);
 
         testing_env!(context
             .predecessor_account_id(accounts(1))
@@ -1519,8 +10879,7 @@ mod tests {
             .build()
         );
 
-        contract.nft_mint("1".to_string(), accounts(2));
-        contract.nft_mint("1".to_string(), accounts(2));
+        let token_id = contract.nft_mint("1".to_string(), accounts(1), None);
 
         testing_env!(context
             .predecessor_account_id(accounts(1))
@@ -1528,12 +10887,23 @@ mod tests {
             .build()
         );
 
-        contract.nft_decrease_series_copies("1".to_string(), U64::from(4));
+        let payout = contract.nft_transfer_payout(
+            accounts(3),
+            token_id,
+            None,
+            Some(U128::from(10u128.pow(24))),
+            Some(10)
+        );
+
+        // creator royalty is folded into the seller residual, no separate entry
+        let mut payout_calc: HashMap<AccountId, U128> = HashMap::new();
+        payout_calc.insert(accounts(1).to_string(), U128::from(10u128.pow(24)));
+
+        assert_eq!(payout.unwrap().payout, payout_calc);
     }
 
     #[test]
-    #[should_panic( expected = "Not for sale" )]
-    fn test_invalid_buy_price_null() {
+    fn test_nft_transfer_payout_creator_not_in_royalty() {
         let (mut context, mut contract) = setup_contract();
         testing_env!(context
             .predecessor_account_id(accounts(1))
@@ -1542,36 +10912,48 @@ mod tests {
         );
 
         let mut royalty: HashMap<AccountId, u32> = HashMap::new();
-        royalty.insert(accounts(1).to_string(), 1000);
+        royalty.insert(accounts(3).to_string(), 1000);
 
-        create_series(&mut contract, &royalty, Some(U128::from(1 * 10u128.pow(24))), None);
+        create_series(&mut contract, &royalty, None, None);
 
         testing_env!(context
             .predecessor_account_id(accounts(1))
-            .attached_deposit(1)
+            .attached_deposit(STORAGE_FOR_MINT)
             .build()
         );
 
-        contract.nft_set_series_price("1".to_string(), None);
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
 
         testing_env!(context
             .predecessor_account_id(accounts(2))
-            .attached_deposit(1 * 10u128.pow(24) + STORAGE_FOR_MINT)
+            .attached_deposit(1)
             .build()
         );
 
-        let token_id = contract.nft_buy("1".to_string(), accounts(2));
+        let payout = contract.nft_transfer_payout(
+            accounts(4),
+            token_id,
+            None,
+            Some(U128::from(10u128.pow(24))),
+            Some(10)
+        );
+
+        // creator is not paid at all on resale
+        let mut payout_calc: HashMap<AccountId, U128> = HashMap::new();
+        payout_calc.insert(
+            accounts(3).to_string(),
+            U128::from((1000 * 10u128.pow(24)) / 10_000)
+        );
+        payout_calc.insert(
+            accounts(2).to_string(),
+            U128::from((9000 * 10u128.pow(24)) / 10_000)
+        );
 
-        let token_from_nft_token = contract.nft_token(token_id);
-        assert_eq!(
-            token_from_nft_token.unwrap().owner_id,
-            accounts(2).to_string()
-        )
+        assert_eq!(payout.unwrap().payout, payout_calc);
     }
 
     #[test]
-    #[should_panic( expected = "Price is higher than 1000000000000000000000000000000000" )]
-    fn test_invalid_price_shouldnt_be_higher_than_max_price() {
+    fn test_nft_transfer_payout_royalty_decay() {
         let (mut context, mut contract) = setup_contract();
         testing_env!(context
             .predecessor_account_id(accounts(1))
@@ -1582,28 +10964,15 @@ mod tests {
         let mut royalty: HashMap<AccountId, u32> = HashMap::new();
         royalty.insert(accounts(1).to_string(), 1000);
 
-        create_series(&mut contract, &royalty, Some(U128::from(1_000_000_000 * 10u128.pow(24))), None);
+        create_series(&mut contract, &royalty, None, None);
 
         testing_env!(context
             .predecessor_account_id(accounts(1))
             .attached_deposit(1)
             .build()
         );
-    }
-
-    #[test]
-    fn test_nft_burn() {
-        let (mut context, mut contract) = setup_contract();
-        testing_env!(context
-            .predecessor_account_id(accounts(1))
-            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
-            .build()
-        );
-
-        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
-        royalty.insert(accounts(1).to_string(), 1000);
 
-        create_series(&mut contract, &royalty, None, None);
+        contract.nft_set_series_royalty_decay("1".to_string(), vec![10_000, 5_000]);
 
         testing_env!(context
             .predecessor_account_id(accounts(1))
@@ -1611,7 +10980,8 @@ mod tests {
             .build()
         );
 
-        let token_id = contract.nft_mint("1".to_string(), accounts(2));
+        let token_id = contract.nft_mint("1".to_string(), accounts(2), None);
+        let balance = 10u128.pow(24);
 
         testing_env!(context
             .predecessor_account_id(accounts(2))
@@ -1619,137 +10989,178 @@ mod tests {
             .build()
         );
 
-        contract.nft_burn(token_id.clone());
-        let token = contract.nft_token(token_id);
-        assert!(token.is_none());
-    }
+        let payout = contract.nft_transfer_payout(
+            accounts(3),
+            token_id.clone(),
+            None,
+            Some(U128::from(balance)),
+            Some(10)
+        ).unwrap();
+        assert_eq!(payout.payout.get(&accounts(1).to_string()), Some(&U128::from(balance * 1000 / 10_000)));
+        assert_eq!(contract.nft_resale_count(token_id.clone()), 1);
+
+        let payout = contract.nft_payout(token_id.clone(), U128::from(balance), 10);
+        assert_eq!(payout.payout.get(&accounts(1).to_string()), Some(&U128::from(balance * 500 / 10_000)));
 
-    #[test]
-    fn test_nft_transfer() {
-        let (mut context, mut contract) = setup_contract();
         testing_env!(context
-            .predecessor_account_id(accounts(1))
-            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
             .build()
         );
 
-        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
-        royalty.insert(accounts(1).to_string(), 1000);
+        let payout = contract.nft_transfer_payout(
+            accounts(2),
+            token_id.clone(),
+            None,
+            Some(U128::from(balance)),
+            Some(10)
+        ).unwrap();
+        assert_eq!(payout.payout.get(&accounts(1).to_string()), Some(&U128::from(balance * 500 / 10_000)));
+        assert_eq!(payout.payout.get(&accounts(3).to_string()), Some(&U128::from(balance * 9_500 / 10_000)));
+        assert_eq!(contract.nft_resale_count(token_id), 2);
+    }
 
-        create_series(&mut contract, &royalty, None, None);
+    #[test]
+    fn test_on_treasury_transfer_failed() {
+        let (mut context, mut contract) = setup_contract();
 
-        testing_env!(context
-            .predecessor_account_id(accounts(1))
-            .attached_deposit(STORAGE_FOR_MINT)
-            .build()
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            VMConfig::default(),
+            RuntimeFeesConfig::default(),
+            Default::default(),
+            vec![PromiseResult::Failed]
         );
-
-        let token_id = contract.nft_mint("1".to_string(), accounts(2));
+        contract.on_treasury_transfer_failed(U128(300));
+        contract.on_treasury_transfer_failed(U128(200));
+        assert_eq!(contract.get_pending_payout(accounts(0)), U128(500));
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            VMConfig::default(),
+            RuntimeFeesConfig::default(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        contract.on_treasury_transfer_failed(U128(300));
+        assert_eq!(contract.get_pending_payout(accounts(0)), U128(500));
 
         testing_env!(context
-            .predecessor_account_id(accounts(2))
+            .predecessor_account_id(accounts(0))
             .attached_deposit(1)
             .build()
         );
-
-        contract.nft_transfer(accounts(3), token_id.clone(), None, None);
-
-        let token = contract.nft_token(token_id).unwrap();
-        assert_eq!(
-            token.owner_id,
-            accounts(3).to_string()
-        )
+        assert_eq!(contract.claim_pending_payout(), U128(500));
+        assert!(get_transfers().contains(&(accounts(0).to_string(), 500)));
+        assert_eq!(contract.get_pending_payout(accounts(0)), U128(0));
     }
 
     #[test]
-    fn test_nft_transfer_unsafe() {
+    fn test_withdrawals_paused() {
         let (mut context, mut contract) = setup_contract();
+
         testing_env!(context
-            .predecessor_account_id(accounts(1))
-            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
             .build()
         );
-
-        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
-        royalty.insert(accounts(1).to_string(), 1000);
-
-        create_series(&mut contract, &royalty, None, None);
+        contract.set_withdrawals_paused(true);
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            VMConfig::default(),
+            RuntimeFeesConfig::default(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        contract.on_treasury_transfer_failed(U128(300));
+        assert_eq!(contract.get_pending_payout(accounts(0)), U128(300));
 
         testing_env!(context
-            .predecessor_account_id(accounts(1))
-            .attached_deposit(STORAGE_FOR_MINT)
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
             .build()
         );
+        contract.set_withdrawals_paused(false);
+        assert_eq!(contract.claim_pending_payout(), U128(300));
+    }
+
+    #[test]
+    #[should_panic(expected = "Withdrawals paused")]
+    fn test_invalid_claim_pending_payout_withdrawals_paused() {
+        let (mut context, mut contract) = setup_contract();
 
-        let token_id = contract.nft_mint("1".to_string(), accounts(2));
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            VMConfig::default(),
+            RuntimeFeesConfig::default(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        contract.on_treasury_transfer_failed(U128(300));
 
         testing_env!(context
-            .predecessor_account_id(accounts(2))
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
             .build()
         );
-
-        contract.nft_transfer_unsafe(accounts(3), token_id.clone(), None, None);
-
-        let token = contract.nft_token(token_id).unwrap();
-        assert_eq!(
-            token.owner_id,
-            accounts(3).to_string()
-        )
+        contract.set_withdrawals_paused(true);
+        contract.claim_pending_payout();
     }
 
     #[test]
-    fn test_nft_transfer_payout() {
+    fn test_fee_schedule() {
         let (mut context, mut contract) = setup_contract();
+
         testing_env!(context
-            .predecessor_account_id(accounts(1))
-            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
             .build()
         );
+        let start_time: Timestamp = 1618109122863866400;
+        contract.set_transaction_fee(100, Some(to_sec(start_time)));
 
-        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
-        royalty.insert(accounts(1).to_string(), 1000);
-
-        create_series(&mut contract, &royalty, None, None);
+        let fee_schedule = contract.fee_schedule();
+        assert_eq!(fee_schedule.current, 300);
+        assert_eq!(fee_schedule.next, Some(100));
+        assert_eq!(fee_schedule.starts_at, Some(to_sec(start_time)));
+        assert_eq!(fee_schedule.effective_now, 300);
 
         testing_env!(context
-            .predecessor_account_id(accounts(1))
-            .attached_deposit(STORAGE_FOR_MINT)
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(start_time + 1)
             .build()
         );
+        let fee_schedule = contract.fee_schedule();
+        assert_eq!(fee_schedule.current, 300);
+        assert_eq!(fee_schedule.next, Some(100));
+        assert_eq!(fee_schedule.effective_now, 100);
+    }
 
-        let token_id = contract.nft_mint("1".to_string(), accounts(2));
+    #[test]
+    fn test_resolved_current_fee_does_not_mutate() {
+        let (mut context, mut contract) = setup_contract();
 
         testing_env!(context
-            .predecessor_account_id(accounts(2))
+            .predecessor_account_id(accounts(0))
             .attached_deposit(1)
             .build()
         );
+        let start_time: Timestamp = 1618109122863866400;
+        contract.set_transaction_fee(100, Some(to_sec(start_time)));
 
-        let payout = contract.nft_transfer_payout(
-            accounts(3),
-            token_id.clone(),
-            Some(0) ,
-            Some(U128::from(1 * 10u128.pow(24))),
-            Some(10)
-        );
-
-        let mut payout_calc: HashMap<AccountId, U128> = HashMap::new();
-        payout_calc.insert(
-            accounts(1).to_string(),
-            U128::from((1000 * (1 * 10u128.pow(24)))/10_000)
-        );
-        payout_calc.insert(
-            accounts(2).to_string(),
-            U128::from((9000 * (1 * 10u128.pow(24))) / 10_000)
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(start_time + 1)
+            .build()
         );
+        assert_eq!(contract.resolved_current_fee(), 100);
+        assert_eq!(contract.calculate_current_transaction_fee(), 100);
+        assert_eq!(contract.calculate_market_data_transaction_fee(&"1".to_string()), 100);
 
-        assert_eq!(payout.unwrap().payout, payout_calc);
-
-        let token = contract.nft_token(token_id).unwrap();
-        assert_eq!(
-            token.owner_id,
-            accounts(3).to_string()
-        )
+        assert_eq!(contract.get_transaction_fee().current_fee, 300);
+        assert_eq!(contract.get_transaction_fee().next_fee, Some(100));
+        assert_eq!(contract.get_transaction_fee().start_time, Some(to_sec(start_time)));
     }
 
     #[test]
@@ -1796,12 +11207,44 @@ mod tests {
             .build()
         );
 
-        contract.calculate_current_transaction_fee();
+        contract.commit_fee_if_due();
         assert_eq!(contract.get_transaction_fee().current_fee, next_fee);
         assert_eq!(contract.get_transaction_fee().next_fee, None);
         assert_eq!(contract.get_transaction_fee().start_time, None);
     }
 
+    #[test]
+    fn test_contract_status() {
+        let (mut context, mut contract) = setup_contract();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+
+        let start_time: Timestamp = 1618109122863866400;
+        contract.set_transaction_fee(100, Some(to_sec(start_time)));
+
+        let status = contract.contract_status();
+        assert_eq!(status.owner_id, accounts(0).to_string());
+        assert_eq!(status.treasury_id, accounts(4).to_string());
+        assert_eq!(status.secondary_treasury_id, accounts(4).to_string());
+        assert_eq!(status.current_fee, 300);
+        assert_eq!(status.scheduled_fee, Some(100));
+        assert_eq!(status.scheduled_fee_start_time, Some(to_sec(start_time)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(start_time + 1)
+            .build()
+        );
+
+        let status = contract.contract_status();
+        assert_eq!(status.current_fee, 100);
+        assert_eq!(status.scheduled_fee, None);
+    }
+
     #[test]
     fn test_transaction_fee_locked() {
         let (mut context, mut contract) = setup_contract();
@@ -1851,7 +11294,7 @@ mod tests {
             .build()
         );
 
-        contract.calculate_current_transaction_fee();
+        contract.commit_fee_if_due();
         assert_eq!(contract.get_transaction_fee().current_fee, next_fee);
         assert_eq!(contract.get_transaction_fee().next_fee, None);
         assert_eq!(contract.get_transaction_fee().start_time, None);
@@ -1860,4 +11303,48 @@ mod tests {
         let series_transaction_fee: u128 = series.transaction_fee.unwrap().into();
         assert_eq!(series_transaction_fee, 300);
     }
+
+    #[test]
+    fn test_refresh_all_series_fees() {
+        let (mut context, mut contract) = setup_contract();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build()
+        );
+
+        let next_fee: u16 = 100;
+        let start_time: Timestamp = 1618109122863866400;
+        contract.set_transaction_fee(next_fee, Some(to_sec(start_time)));
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build()
+        );
+
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), None);
+        create_series(&mut contract, &royalty, Some(U128::from(10u128.pow(24))), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(start_time + 1)
+            .attached_deposit(1)
+            .build()
+        );
+
+        assert_eq!(contract.refresh_all_series_fees(None, Some(1)), 1);
+        assert_eq!(contract.get_transaction_fee().current_fee, next_fee);
+        assert_eq!(contract.refresh_all_series_fees(Some(U128::from(1)), Some(1)), 1);
+
+        for token_series_id in ["1", "2"].iter() {
+            let series = contract.nft_get_series_single(token_series_id.to_string());
+            let series_transaction_fee: u128 = series.transaction_fee.unwrap().into();
+            assert_eq!(series_transaction_fee, next_fee as u128);
+        }
+    }
 }